@@ -1,56 +1,177 @@
-use tauri::{AppHandle, Manager};
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter, Manager, Runtime};
 
 use crate::{
-    settings::{api_key_file_path, litellm_api_key_file_path, Settings},
+    config::{AppConfig, CONFIG},
+    error::MiloError,
+    key_status::{self, KeyStatus},
+    models::{self, ModelInfo, MODEL_CATALOG_RETRY_DELAY, MODEL_CATALOG_TTL},
+    providers::shared_client,
+    secrets::{self, API_KEY, LITELLM_API_KEY},
+    settings::Settings,
     state::AppState,
+    tray,
 };
-use std::fs;
 
 #[tauri::command]
 pub async fn save_api_key(key: String) -> Result<(), String> {
-    fs::write(api_key_file_path(), key).map_err(|e| e.to_string())
+    save_or_delete(API_KEY, &key)
 }
 
 #[tauri::command]
 pub async fn get_api_key() -> Result<String, String> {
-    fs::read_to_string(api_key_file_path()).map_err(|e| e.to_string())
+    secrets::load_secret(API_KEY)?.ok_or_else(|| "No API key saved".to_string())
 }
 
 #[tauri::command]
-pub async fn save_litellm_api_key(key: String) -> Result<(), String> {
-    fs::write(litellm_api_key_file_path(), key).map_err(|e| e.to_string())
+pub async fn save_litellm_api_key(app: AppHandle, key: String) -> Result<(), String> {
+    save_or_delete(LITELLM_API_KEY, &key)?;
+    let state = app.state::<AppState>();
+    state.key_status.clear();
+    state.model_catalog.clear();
+    state.model_catalog_error.clear();
+
+    // Update the tray warning for the new key
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = refresh_key_status(&app, true).await {
+            println!("⚠️ Failed to check API key: {}", e);
+        }
+    });
+    Ok(())
 }
 
 #[tauri::command]
 pub async fn get_litellm_api_key() -> Result<String, String> {
-    fs::read_to_string(litellm_api_key_file_path()).map_err(|e| e.to_string())
+    secrets::load_secret(LITELLM_API_KEY)?.ok_or_else(|| "No LiteLLM API key saved".to_string())
+}
+
+// Saving an empty key is how the settings page removes it
+fn save_or_delete(name: &str, key: &str) -> Result<(), String> {
+    if key.is_empty() {
+        secrets::delete_secret(name)
+    } else {
+        secrets::save_secret(name, key)
+    }
 }
 
 #[tauri::command]
 pub async fn get_usage_key_preview() -> Result<String, String> {
-    match fs::read_to_string(litellm_api_key_file_path()) {
-        Ok(key) => {
-            if key.is_empty() {
-                Ok("".to_string())
-            } else if key.len() <= 8 {
-                Ok("****".to_string())
-            } else {
-                let visible_chars = std::cmp::min(4, key.len() / 3);
-                let prefix = &key[..visible_chars];
-                let suffix_start = key.len() - visible_chars;
-                let suffix = &key[suffix_start..];
-                Ok(format!("{}****{}", prefix, suffix))
+    match secrets::load_secret(LITELLM_API_KEY) {
+        Ok(Some(key)) => Ok(secrets::key_preview(&key)),
+        Ok(None) | Err(_) => Ok("".to_string()),
+    }
+}
+
+/// Checks the LiteLLM key against the proxy: whether it works, which models
+/// it may use and how much budget is left
+#[tauri::command]
+pub async fn validate_api_key(app: AppHandle, force: Option<bool>) -> Result<KeyStatus, MiloError> {
+    refresh_key_status(&app, force.unwrap_or(false)).await
+}
+
+/// Checks the LiteLLM key, reusing a recent result unless `force` is set, and
+/// flags the tray icon when the key is unusable or running out of budget
+pub async fn refresh_key_status<R: Runtime>(
+    app: &AppHandle<R>,
+    force: bool,
+) -> Result<KeyStatus, MiloError> {
+    let state = app.state::<AppState>();
+    let settings = state.settings.lock().await.clone();
+
+    let status = match secrets::load_secret(LITELLM_API_KEY).map_err(MiloError::Settings)? {
+        None => KeyStatus::invalid("No API key saved"),
+        Some(api_key) => {
+            let max_age = Duration::from_secs(settings.key_check.cache_minutes * 60);
+            if let Some(status) = state.key_status.get(&api_key, max_age).filter(|_| !force) {
+                return Ok(status);
             }
+            let http = shared_client(&settings.timeouts)?;
+            let status = key_status::check_key(&http, &CONFIG.litellm_base_url, &api_key).await?;
+            state.key_status.store(&api_key, status.clone());
+            status
+        }
+    };
+
+    let warning = status.warning(settings.key_check.low_budget_threshold);
+    if let Some(warning) = &warning {
+        println!("⚠️ {}", warning);
+    }
+    tray::show_key_warning(app, warning.as_deref());
+    let _ = app.emit("key-status-changed", &status);
+    Ok(status)
+}
+
+/// Config in effect after the user's config.json and `MILO_*` variables
+#[tauri::command]
+pub fn get_app_config() -> AppConfig {
+    CONFIG.clone()
+}
+
+/// Models the LiteLLM proxy offers, with their context windows where known
+#[tauri::command]
+pub async fn list_available_models(
+    app: AppHandle,
+    force: Option<bool>,
+) -> Result<Vec<ModelInfo>, MiloError> {
+    model_catalog(&app, force.unwrap_or(false)).await
+}
+
+/// The proxy's models, reused for `MODEL_CATALOG_TTL` unless `force` is set.
+/// A failed lookup is returned again for `MODEL_CATALOG_RETRY_DELAY`.
+pub async fn model_catalog<R: Runtime>(
+    app: &AppHandle<R>,
+    force: bool,
+) -> Result<Vec<ModelInfo>, MiloError> {
+    let state = app.state::<AppState>();
+    let base_url = &CONFIG.litellm_base_url;
+    if !force {
+        if let Some(catalog) = state.model_catalog.get(base_url, MODEL_CATALOG_TTL) {
+            return Ok(catalog);
+        }
+        if let Some(error) = state
+            .model_catalog_error
+            .get(base_url, MODEL_CATALOG_RETRY_DELAY)
+        {
+            return Err(error);
         }
-        Err(_) => Ok("".to_string()),
     }
+
+    let api_key = secrets::load_secret(LITELLM_API_KEY)
+        .map_err(MiloError::Settings)?
+        .ok_or_else(|| MiloError::Settings("No API key saved".to_string()))?;
+    let timeouts = state.settings.lock().await.timeouts.clone();
+    let fetched = models::fetch_catalog(&shared_client(&timeouts)?, base_url, &api_key).await;
+    let catalog = match fetched {
+        Ok(catalog) => catalog,
+        Err(e) => {
+            state.model_catalog_error.store(base_url, e.clone());
+            return Err(e);
+        }
+    };
+    println!("📚 Proxy offers {} models", catalog.len());
+    state.model_catalog_error.clear();
+    state.model_catalog.store(base_url, catalog.clone());
+    Ok(catalog)
 }
 
 #[tauri::command]
 pub async fn save_settings(
+    app: AppHandle,
     state: tauri::State<'_, AppState>,
     settings: Settings,
 ) -> Result<(), String> {
+    settings.validate()?;
+    // Only models not in use before are checked, so other saves never wait on the proxy.
+    // They can only be checked while it is reachable; offline, settings still save.
+    let current = state.settings.lock().await.clone();
+    if !settings.new_proxy_models(&current).is_empty() {
+        match model_catalog(&app, false).await {
+            Ok(catalog) if catalog.is_empty() => {}
+            Ok(catalog) => settings.validate_models(&catalog, &current)?,
+            Err(e) => println!("⚠️ Skipping model check: {}", e),
+        }
+    }
     settings.save()?;
     *state.settings.lock().await = settings;
     Ok(())
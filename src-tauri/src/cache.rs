@@ -0,0 +1,62 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Keeps the last result of a network lookup, reused until it is too old or
+/// asked for under a different key, e.g. after the API key changed
+pub struct TtlCache<T> {
+    cached: Mutex<Option<Cached<T>>>,
+}
+
+struct Cached<T> {
+    key: String,
+    stored: Instant,
+    value: T,
+}
+
+impl<T> Default for TtlCache<T> {
+    fn default() -> Self {
+        Self {
+            cached: Mutex::new(None),
+        }
+    }
+}
+
+impl<T: Clone> TtlCache<T> {
+    pub fn get(&self, key: &str, max_age: Duration) -> Option<T> {
+        let cached = self.cached.lock().unwrap();
+        cached
+            .as_ref()
+            .filter(|cached| cached.key == key && cached.stored.elapsed() < max_age)
+            .map(|cached| cached.value.clone())
+    }
+
+    pub fn store(&self, key: &str, value: T) {
+        *self.cached.lock().unwrap() = Some(Cached {
+            key: key.to_string(),
+            stored: Instant::now(),
+            value,
+        });
+    }
+
+    pub fn clear(&self) {
+        self.cached.lock().unwrap().take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_is_per_key_and_expires() {
+        let cache = TtlCache::default();
+        cache.store("sk-one", "Invalid API key".to_string());
+
+        assert!(cache.get("sk-one", Duration::from_secs(60)).is_some());
+        assert!(cache.get("sk-two", Duration::from_secs(60)).is_none());
+        assert!(cache.get("sk-one", Duration::ZERO).is_none());
+
+        cache.clear();
+        assert!(cache.get("sk-one", Duration::from_secs(60)).is_none());
+    }
+}
@@ -0,0 +1,351 @@
+use jieba_rs::Jieba;
+
+use crate::error::MiloError;
+use crate::history::SENTENCE_ENDINGS;
+use crate::settings::ChunkingSettings;
+
+/// A piece of the input sent to the model on its own, together with the
+/// whitespace that followed it in the original text
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    pub text: String,
+    pub separator: String,
+}
+
+/// Splits `text` into chunks of at most `max_chars` characters, breaking at
+/// paragraph boundaries first, then sentences, then words. A single word
+/// longer than `max_chars` becomes its own oversized chunk.
+pub fn split_into_chunks(text: &str, max_chars: usize) -> Vec<Chunk> {
+    let max_chars = max_chars.max(1);
+    if text.chars().count() <= max_chars {
+        return vec![Chunk {
+            text: text.to_string(),
+            separator: String::new(),
+        }];
+    }
+
+    let mut units = Vec::new();
+    for paragraph in split_paragraphs(text) {
+        if char_len(&paragraph.text) <= max_chars {
+            units.push(paragraph);
+            continue;
+        }
+        for sentence in split_sentences(&paragraph.text) {
+            if char_len(&sentence.text) <= max_chars {
+                units.push(sentence);
+            } else {
+                units.extend(split_words(&sentence.text));
+            }
+        }
+        // The paragraph break belongs after the paragraph's last piece
+        if let Some(last) = units.last_mut() {
+            last.separator.push_str(&paragraph.separator);
+        }
+    }
+
+    pack(units, max_chars)
+}
+
+/// Rough characters per token, to turn a context window into characters
+const CHARS_PER_TOKEN: usize = 4;
+
+/// The configured chunk size, shrunk so that a chunk and a rewrite of the
+/// same length fit in the model's `context_window` tokens
+pub fn max_chunk_chars(chunk_chars: usize, context_window: Option<u32>) -> usize {
+    match context_window {
+        Some(tokens) => chunk_chars.min(tokens as usize * CHARS_PER_TOKEN / 2),
+        None => chunk_chars,
+    }
+}
+
+/// Refuses texts over the configured hard limit, or over `chunk_chars` (the
+/// effective chunk size, see `max_chunk_chars`) when chunking is turned off
+pub fn check_input_size(
+    text: &str,
+    settings: &ChunkingSettings,
+    chunk_chars: usize,
+) -> Result<(), MiloError> {
+    let chars = char_len(text);
+    let limit = if settings.refuse_oversized {
+        chunk_chars.min(settings.max_input_chars)
+    } else {
+        settings.max_input_chars
+    };
+    if chars > limit {
+        return Err(MiloError::InputTooLarge { chars, limit });
+    }
+    Ok(())
+}
+
+/// Joins transformed chunks back together using the original separators
+pub fn stitch(chunks: &[Chunk], outputs: &[String]) -> String {
+    let mut result = String::new();
+    for (chunk, output) in chunks.iter().zip(outputs) {
+        result.push_str(trim_separators(output));
+        result.push_str(&chunk.separator);
+    }
+    result
+}
+
+// Drops the blank lines a model puts around its output, which would double up
+// with the separators, but keeps the first line's indentation
+fn trim_separators(output: &str) -> &str {
+    let body = output.trim_end();
+    let leading = body.len() - body.trim_start().len();
+    match body[..leading].rfind('\n') {
+        Some(newline) => &body[newline + 1..],
+        None => body,
+    }
+}
+
+fn char_len(text: &str) -> usize {
+    text.chars().count()
+}
+
+// Greedily merges adjacent units while they fit in `max_chars`
+fn pack(units: Vec<Chunk>, max_chars: usize) -> Vec<Chunk> {
+    let mut chunks: Vec<Chunk> = Vec::new();
+
+    for unit in units {
+        match chunks.last_mut() {
+            Some(current)
+                if char_len(&current.text)
+                    + char_len(&current.separator)
+                    + char_len(&unit.text)
+                    <= max_chars =>
+            {
+                current.text.push_str(&current.separator);
+                current.text.push_str(&unit.text);
+                current.separator = unit.separator;
+            }
+            _ => chunks.push(unit),
+        }
+    }
+
+    chunks
+}
+
+// Paragraphs are separated by one or more blank lines
+fn split_paragraphs(text: &str) -> Vec<Chunk> {
+    let mut paragraphs = Vec::new();
+    let mut content = String::new();
+    let mut blank_lines = String::new();
+
+    for line in text.split_inclusive('\n') {
+        if line.trim().is_empty() && !content.is_empty() {
+            blank_lines.push_str(line);
+            continue;
+        }
+        if !blank_lines.is_empty() {
+            paragraphs.push(finish_paragraph(&content, &blank_lines));
+            content.clear();
+            blank_lines.clear();
+        }
+        content.push_str(line);
+    }
+
+    if !content.is_empty() {
+        paragraphs.push(finish_paragraph(&content, &blank_lines));
+    }
+    paragraphs
+}
+
+// Moves the paragraph's trailing line break into its separator
+fn finish_paragraph(content: &str, blank_lines: &str) -> Chunk {
+    let body = content.trim_end_matches(['\r', '\n']);
+    Chunk {
+        text: body.to_string(),
+        separator: format!("{}{}", &content[body.len()..], blank_lines),
+    }
+}
+
+// Breaks after sentence-ending punctuation, keeping the following whitespace as separator
+fn split_sentences(text: &str) -> Vec<Chunk> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((_, ch)) = chars.next() {
+        if !SENTENCE_ENDINGS.contains(&ch) {
+            continue;
+        }
+        // Keep runs like "..." or "?!" in the same sentence
+        while let Some(&(_, next)) = chars.peek() {
+            if SENTENCE_ENDINGS.contains(&next) {
+                chars.next();
+            } else {
+                break;
+            }
+        }
+
+        let end = chars.peek().map_or(text.len(), |&(index, _)| index);
+        let mut separator_end = end;
+        while let Some(&(index, next)) = chars.peek() {
+            if next.is_whitespace() {
+                chars.next();
+                separator_end = index + next.len_utf8();
+            } else {
+                break;
+            }
+        }
+
+        // "3.14" or "e.g.x" are not sentence breaks; full-width punctuation needs no space
+        let is_full_width = !ch.is_ascii();
+        if separator_end > end || is_full_width || separator_end == text.len() {
+            sentences.push(Chunk {
+                text: text[start..end].to_string(),
+                separator: text[end..separator_end].to_string(),
+            });
+            start = separator_end;
+        }
+    }
+
+    if start < text.len() {
+        sentences.push(Chunk {
+            text: text[start..].to_string(),
+            separator: String::new(),
+        });
+    }
+    sentences
+}
+
+// Last resort for overlong sentences: words for spaced scripts, jieba tokens for CJK
+fn split_words(text: &str) -> Vec<Chunk> {
+    let has_chinese = text.chars().any(|c| matches!(c, '\u{4e00}'..='\u{9fff}'));
+    if has_chinese {
+        return Jieba::new()
+            .cut(text, false)
+            .into_iter()
+            .map(|word| Chunk {
+                text: word.to_string(),
+                separator: String::new(),
+            })
+            .collect();
+    }
+
+    let mut words = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let word_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let after_word = &rest[word_end..];
+        let separator_len = after_word.len() - after_word.trim_start().len();
+        words.push(Chunk {
+            text: rest[..word_end].to_string(),
+            separator: after_word[..separator_len].to_string(),
+        });
+        rest = &after_word[separator_len..];
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejoin(chunks: &[Chunk]) -> String {
+        let outputs: Vec<String> = chunks.iter().map(|c| c.text.clone()).collect();
+        stitch(chunks, &outputs)
+    }
+
+    #[test]
+    fn test_short_text_is_single_chunk() {
+        let chunks = split_into_chunks("Hello world.", 100);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].text, "Hello world.");
+    }
+
+    #[test]
+    fn test_splits_on_paragraphs_and_round_trips() {
+        let text = "First paragraph here.\n\nSecond paragraph here.\n\n\nThird one.";
+        let chunks = split_into_chunks(text, 30);
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].text, "First paragraph here.");
+        assert_eq!(chunks[0].separator, "\n\n");
+        assert_eq!(chunks[1].separator, "\n\n\n");
+        assert_eq!(rejoin(&chunks), text);
+    }
+
+    #[test]
+    fn test_long_paragraph_splits_on_sentences() {
+        let text = "One sentence here. Another sentence there! A third? Pi is 3.14 ok.";
+        let chunks = split_into_chunks(text, 25);
+
+        assert!(chunks.iter().all(|c| c.text.chars().count() <= 25));
+        assert_eq!(chunks[0].text, "One sentence here.");
+        assert!(chunks.iter().any(|c| c.text.ends_with("Pi is 3.14 ok.")));
+        assert_eq!(rejoin(&chunks), text);
+    }
+
+    #[test]
+    fn test_cjk_sentences() {
+        let text = "你好世界。今天天气很好！我们去公园吧。";
+        let chunks = split_into_chunks(text, 8);
+
+        assert_eq!(chunks[0].text, "你好世界。");
+        assert_eq!(rejoin(&chunks), text);
+    }
+
+    #[test]
+    fn test_check_input_size() {
+        let mut settings = ChunkingSettings {
+            chunk_chars: 10,
+            max_input_chars: 20,
+            ..Default::default()
+        };
+        assert!(check_input_size("fifteen chars!!", &settings, 10).is_ok());
+        assert_eq!(
+            check_input_size(&"x".repeat(21), &settings, 10),
+            Err(MiloError::InputTooLarge {
+                chars: 21,
+                limit: 20
+            })
+        );
+
+        settings.refuse_oversized = true;
+        assert!(check_input_size("fifteen chars!!", &settings, 10).is_err());
+        // A model with a small context window lowers the limit like it lowers the chunks
+        settings.chunk_chars = 6000;
+        let chunk_chars = max_chunk_chars(settings.chunk_chars, Some(4));
+        assert_eq!(
+            check_input_size("ten chars!", &settings, chunk_chars),
+            Err(MiloError::InputTooLarge {
+                chars: 10,
+                limit: 8
+            })
+        );
+        assert_eq!(split_into_chunks("ten chars!", chunk_chars).len(), 2);
+    }
+
+    #[test]
+    fn test_stitch_keeps_indentation_and_blank_lines() {
+        let chunks = split_into_chunks("  Indented start.\n\nSecond part.", 20);
+        assert_eq!(chunks.len(), 2);
+
+        let outputs = vec![
+            "\n  Indented rewrite.\n\n".to_string(),
+            "First line.\n\n    Still indented.\n".to_string(),
+        ];
+        assert_eq!(
+            stitch(&chunks, &outputs),
+            "  Indented rewrite.\n\nFirst line.\n\n    Still indented."
+        );
+    }
+
+    #[test]
+    fn test_max_chunk_chars_fits_context_window() {
+        assert_eq!(max_chunk_chars(6000, None), 6000);
+        assert_eq!(max_chunk_chars(6000, Some(128_000)), 6000);
+        // 2048 tokens hold about 4096 characters of input plus as much output
+        assert_eq!(max_chunk_chars(6000, Some(2048)), 4096);
+    }
+
+    #[test]
+    fn test_overlong_sentence_falls_back_to_words() {
+        let text = "alpha beta gamma delta epsilon zeta";
+        let chunks = split_into_chunks(text, 12);
+
+        assert!(chunks.iter().all(|c| c.text.chars().count() <= 12));
+        assert_eq!(rejoin(&chunks), text);
+    }
+}
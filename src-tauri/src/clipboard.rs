@@ -0,0 +1,313 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use arboard::{Clipboard, ImageData};
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::error::MiloError;
+use crate::settings::ClipboardRestoreSettings;
+use crate::state::AppState;
+
+/// Time for the focused app to read the clipboard after a simulated paste
+pub const PASTE_SETTLE_DELAY: Duration = Duration::from_millis(200);
+
+// Tells apart restores scheduled by different transforms
+static NEXT_RESTORE_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Clipboard access for the transform pipeline, so it can run without a display
+pub trait ClipboardBackend: Send + Sync {
+    fn get_text(&self) -> Result<String, MiloError>;
+    fn get_html(&self) -> Result<String, MiloError>;
+    fn get_image(&self) -> Result<ImageData<'static>, MiloError>;
+    fn set_text(&self, text: &str) -> Result<(), MiloError>;
+    /// Sets rich text with a plain-text alternative for apps that only paste text
+    fn set_html(&self, html: &str, alt_text: Option<&str>) -> Result<(), MiloError>;
+    fn set_image(&self, image: ImageData<'static>) -> Result<(), MiloError>;
+    fn clear(&self) -> Result<(), MiloError>;
+}
+
+/// The system clipboard through arboard. It is opened on first use and kept
+/// open, since on Linux the clipboard contents live as long as the handle.
+#[derive(Default)]
+pub struct ArboardClipboard {
+    clipboard: Mutex<Option<Clipboard>>,
+}
+
+impl ArboardClipboard {
+    fn with<T>(
+        &self,
+        action: &str,
+        f: impl FnOnce(&mut Clipboard) -> Result<T, arboard::Error>,
+    ) -> Result<T, MiloError> {
+        let mut clipboard = self.clipboard.lock().unwrap();
+        if clipboard.is_none() {
+            let opened = Clipboard::new().map_err(|e| MiloError::Clipboard(e.to_string()))?;
+            *clipboard = Some(opened);
+        }
+        f(clipboard.as_mut().unwrap())
+            .map_err(|e| MiloError::Clipboard(format!("Failed to {}: {}", action, e)))
+    }
+}
+
+impl ClipboardBackend for ArboardClipboard {
+    fn get_text(&self) -> Result<String, MiloError> {
+        self.with("get clipboard text", |clipboard| clipboard.get_text())
+    }
+
+    fn get_html(&self) -> Result<String, MiloError> {
+        self.with("get clipboard HTML", |clipboard| clipboard.get().html())
+    }
+
+    fn get_image(&self) -> Result<ImageData<'static>, MiloError> {
+        self.with("get clipboard image", |clipboard| clipboard.get_image())
+    }
+
+    fn set_text(&self, text: &str) -> Result<(), MiloError> {
+        self.with("set clipboard text", |clipboard| clipboard.set_text(text))
+    }
+
+    fn set_html(&self, html: &str, alt_text: Option<&str>) -> Result<(), MiloError> {
+        self.with("set clipboard HTML", |clipboard| {
+            clipboard.set_html(html, alt_text)
+        })
+    }
+
+    fn set_image(&self, image: ImageData<'static>) -> Result<(), MiloError> {
+        self.with("set clipboard image", |clipboard| {
+            clipboard.set_image(image)
+        })
+    }
+
+    fn clear(&self) -> Result<(), MiloError> {
+        self.with("clear clipboard", |clipboard| clipboard.clear())
+    }
+}
+
+/// Clipboard contents saved before a transform so they can be put back later
+#[derive(Default)]
+pub struct ClipboardSnapshot {
+    text: Option<String>,
+    html: Option<String>,
+    image: Option<ImageData<'static>>,
+}
+
+impl ClipboardSnapshot {
+    /// Saves every format Milo can write back; missing formats are skipped
+    pub fn capture(clipboard: &dyn ClipboardBackend) -> Self {
+        Self {
+            text: clipboard.get_text().ok(),
+            html: clipboard.get_html().ok(),
+            image: clipboard.get_image().ok(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_none() && self.html.is_none() && self.image.is_none()
+    }
+
+    /// Writes the saved contents back. Only one format can be written at a time,
+    /// so an image wins over text because nothing else could recreate it.
+    pub fn restore(&self, clipboard: &dyn ClipboardBackend) -> Result<(), MiloError> {
+        if let Some(image) = &self.image {
+            clipboard.set_image(image.clone())
+        } else if let Some(html) = &self.html {
+            clipboard.set_html(html, self.text.as_deref())
+        } else if let Some(text) = &self.text {
+            clipboard.set_text(text)
+        } else {
+            clipboard.clear()
+        }
+    }
+}
+
+/// A restore waiting for its delay
+pub struct PendingRestore {
+    id: u64,
+    snapshot: ClipboardSnapshot,
+    /// Clipboard text while the result is on it; anything else means the user
+    /// copied something new, which must not be overwritten
+    placed_text: String,
+}
+
+/// Takes the snapshot of a restore that hasn't run yet. A transform started in
+/// the meantime stashes that instead of the previous result.
+pub fn take_pending_snapshot<R: Runtime>(handle: &AppHandle<R>) -> Option<ClipboardSnapshot> {
+    let state = handle.state::<AppState>();
+    let pending = state.pending_restore.lock().unwrap().take();
+    pending.map(|pending| pending.snapshot)
+}
+
+/// Puts `snapshot` back once `settings.delay_ms` has passed
+pub fn schedule_restore<R: Runtime>(
+    handle: &AppHandle<R>,
+    snapshot: ClipboardSnapshot,
+    placed_text: String,
+    settings: &ClipboardRestoreSettings,
+) {
+    let id = NEXT_RESTORE_ID.fetch_add(1, Ordering::SeqCst);
+    *handle.state::<AppState>().pending_restore.lock().unwrap() = Some(PendingRestore {
+        id,
+        snapshot,
+        placed_text,
+    });
+
+    let handle = handle.clone();
+    let delay = Duration::from_millis(settings.delay_ms);
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(delay).await;
+        restore_pending(&handle, id);
+    });
+    println!("📋 Previous clipboard will be restored in {:?}", delay);
+}
+
+// Restores the stashed contents of restore `id` if the result is still on the
+// clipboard. A later transform replaces the pending restore, which skips this one.
+fn restore_pending<R: Runtime>(handle: &AppHandle<R>, id: u64) {
+    let state = handle.state::<AppState>();
+    let pending = {
+        let mut pending_restore = state.pending_restore.lock().unwrap();
+        match pending_restore.as_ref() {
+            Some(pending) if pending.id == id => pending_restore.take(),
+            _ => None,
+        }
+    };
+    let Some(pending) = pending else {
+        return;
+    };
+
+    let clipboard = state.clipboard.as_ref();
+    if clipboard.get_text().ok().as_deref() != Some(pending.placed_text.as_str()) {
+        println!("📋 Clipboard changed since the transform, not restoring it");
+        return;
+    }
+    match pending.snapshot.restore(clipboard) {
+        Ok(()) => println!("📋 Previous clipboard restored"),
+        Err(e) => println!("⚠️ {}", e),
+    }
+}
+
+/// Keeps the clipboard in memory, for running the pipeline in tests
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryClipboard {
+    contents: Mutex<ClipboardSnapshot>,
+}
+
+#[cfg(test)]
+impl MemoryClipboard {
+    pub fn with_text(text: &str) -> Self {
+        let clipboard = Self::default();
+        clipboard.set_text(text).unwrap();
+        clipboard
+    }
+
+    fn missing(format: &str) -> MiloError {
+        MiloError::Clipboard(format!("No {} on the clipboard", format))
+    }
+
+    fn replace(&self, contents: ClipboardSnapshot) -> Result<(), MiloError> {
+        *self.contents.lock().unwrap() = contents;
+        Ok(())
+    }
+}
+
+// Like the system clipboard, every write replaces all formats at once
+#[cfg(test)]
+impl ClipboardBackend for MemoryClipboard {
+    fn get_text(&self) -> Result<String, MiloError> {
+        let contents = self.contents.lock().unwrap();
+        contents.text.clone().ok_or_else(|| Self::missing("text"))
+    }
+
+    fn get_html(&self) -> Result<String, MiloError> {
+        let contents = self.contents.lock().unwrap();
+        contents.html.clone().ok_or_else(|| Self::missing("HTML"))
+    }
+
+    fn get_image(&self) -> Result<ImageData<'static>, MiloError> {
+        let contents = self.contents.lock().unwrap();
+        contents.image.clone().ok_or_else(|| Self::missing("image"))
+    }
+
+    fn set_text(&self, text: &str) -> Result<(), MiloError> {
+        self.replace(ClipboardSnapshot {
+            text: Some(text.to_string()),
+            ..Default::default()
+        })
+    }
+
+    fn set_html(&self, html: &str, alt_text: Option<&str>) -> Result<(), MiloError> {
+        self.replace(ClipboardSnapshot {
+            text: alt_text.map(str::to_string),
+            html: Some(html.to_string()),
+            image: None,
+        })
+    }
+
+    fn set_image(&self, image: ImageData<'static>) -> Result<(), MiloError> {
+        self.replace(ClipboardSnapshot {
+            image: Some(image),
+            ..Default::default()
+        })
+    }
+
+    fn clear(&self) -> Result<(), MiloError> {
+        self.replace(ClipboardSnapshot::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_snapshot_restores_rich_text() {
+        let clipboard = MemoryClipboard::default();
+        clipboard
+            .set_html("<p><b>Hello</b></p>", Some("Hello"))
+            .unwrap();
+        let snapshot = ClipboardSnapshot::capture(&clipboard);
+
+        clipboard.set_text("transformed").unwrap();
+        snapshot.restore(&clipboard).unwrap();
+
+        assert_eq!(clipboard.get_html().unwrap(), "<p><b>Hello</b></p>");
+        assert_eq!(clipboard.get_text().unwrap(), "Hello");
+    }
+
+    #[test]
+    fn test_snapshot_restores_image() {
+        let clipboard = MemoryClipboard::default();
+        clipboard
+            .set_image(ImageData {
+                width: 1,
+                height: 1,
+                bytes: Cow::Owned(vec![255, 0, 0, 255]),
+            })
+            .unwrap();
+        let snapshot = ClipboardSnapshot::capture(&clipboard);
+        assert!(!snapshot.is_empty());
+
+        clipboard.set_text("transformed").unwrap();
+        snapshot.restore(&clipboard).unwrap();
+
+        assert!(clipboard.get_text().is_err());
+        assert_eq!(
+            clipboard.get_image().unwrap().bytes.as_ref(),
+            &[255, 0, 0, 255]
+        );
+    }
+
+    #[test]
+    fn test_empty_snapshot_clears_clipboard() {
+        let clipboard = MemoryClipboard::default();
+        let snapshot = ClipboardSnapshot::capture(&clipboard);
+        assert!(snapshot.is_empty());
+
+        clipboard.set_text("transformed").unwrap();
+        snapshot.restore(&clipboard).unwrap();
+        assert!(clipboard.get_text().is_err());
+    }
+}
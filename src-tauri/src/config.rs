@@ -1,17 +1,68 @@
+use dirs::config_dir;
 use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct AppConfig {
     pub litellm_base_url: String,
     pub website_url: String,
     pub default_model: String,
 }
 
+// Fields a user config file sets; the rest keep their embedded values
+#[derive(Debug, Default, Deserialize)]
+struct ConfigOverrides {
+    litellm_base_url: Option<String>,
+    website_url: Option<String>,
+    default_model: Option<String>,
+}
+
 impl AppConfig {
+    /// Embedded defaults, overridden by `config.json` in the milo config dir
+    /// and then by `MILO_*` environment variables, so self-hosted setups
+    /// don't need a custom build
     pub fn load() -> Self {
-        // Load from embedded config file
-        let config_str = include_str!("../config.json");
-        serde_json::from_str(config_str).unwrap_or_else(|_| Self::default())
+        let user_config = fs::read_to_string(user_config_path()).ok();
+        Self::layered(
+            include_str!("../config.json"),
+            user_config.as_deref(),
+            |name| std::env::var(name).ok(),
+        )
+    }
+
+    fn layered(
+        embedded: &str,
+        user_config: Option<&str>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Self {
+        let mut config: Self = serde_json::from_str(embedded).unwrap_or_else(|_| Self::default());
+
+        if let Some(contents) = user_config {
+            match serde_json::from_str::<ConfigOverrides>(contents) {
+                Ok(overrides) => config.apply(overrides),
+                Err(e) => println!("⚠️ Ignoring invalid user config.json: {}", e),
+            }
+        }
+
+        let env = |name: &str| env(name).filter(|value| !value.trim().is_empty());
+        config.apply(ConfigOverrides {
+            litellm_base_url: env("MILO_LITELLM_BASE_URL"),
+            website_url: env("MILO_WEBSITE_URL"),
+            default_model: env("MILO_DEFAULT_MODEL"),
+        });
+        config
+    }
+
+    fn apply(&mut self, overrides: ConfigOverrides) {
+        if let Some(litellm_base_url) = overrides.litellm_base_url {
+            self.litellm_base_url = litellm_base_url;
+        }
+        if let Some(website_url) = overrides.website_url {
+            self.website_url = website_url;
+        }
+        if let Some(default_model) = overrides.default_model {
+            self.default_model = default_model;
+        }
     }
 
     fn default() -> Self {
@@ -23,7 +74,48 @@ impl AppConfig {
     }
 }
 
+/// User-level config file, next to settings.json
+pub fn user_config_path() -> PathBuf {
+    let mut path = config_dir().unwrap_or_else(|| PathBuf::from("."));
+    path.push("milo");
+    path.push("config.json");
+    path
+}
+
 // Global config instance
 lazy_static::lazy_static! {
     pub static ref CONFIG: AppConfig = AppConfig::load();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMBEDDED: &str = r#"{
+        "litellm_base_url": "https://proxy.example.com",
+        "website_url": "https://example.com",
+        "default_model": "gpt-4o-mini"
+    }"#;
+
+    #[test]
+    fn test_later_layers_override_earlier_ones() {
+        let config = AppConfig::layered(
+            EMBEDDED,
+            Some(
+                r#"{ "litellm_base_url": "https://litellm.internal", "default_model": "llama3" }"#,
+            ),
+            |name| (name == "MILO_DEFAULT_MODEL").then(|| "gpt-4o".to_string()),
+        );
+
+        assert_eq!(config.litellm_base_url, "https://litellm.internal");
+        assert_eq!(config.website_url, "https://example.com");
+        assert_eq!(config.default_model, "gpt-4o");
+    }
+
+    #[test]
+    fn test_invalid_layers_are_ignored() {
+        let config = AppConfig::layered(EMBEDDED, Some("{ not json"), |_| Some("  ".to_string()));
+        assert_eq!(config, AppConfig::layered(EMBEDDED, None, |_| None));
+        assert_eq!(config.litellm_base_url, "https://proxy.example.com");
+    }
+}
@@ -1,58 +1,607 @@
-use arboard::Clipboard;
-use tauri::Manager;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 
-use crate::api::get_litellm_api_key;
-use crate::history::add_transformation_to_history;
-use crate::transform::transform_text;
+use futures::{stream, StreamExt, TryStreamExt};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+use tokio_util::sync::CancellationToken;
 
-// Helper function to clean text while preserving formatting
-fn clean_text(text: &str) -> String {
-    text.lines()
-        .map(|line| line.trim())
-        .collect::<Vec<_>>()
-        .join("\n")
+use crate::chunking::{self, Chunk};
+use crate::clipboard::{ClipboardBackend, ClipboardSnapshot, PASTE_SETTLE_DELAY};
+use crate::config::CONFIG;
+use crate::error::MiloError;
+use crate::formatting::{
+    clean_text, has_placeholders, protect_code, restore_indentation, ProtectedText,
+    PLACEHOLDER_INSTRUCTION,
+};
+use crate::history::{
+    record_cancellation, record_entry, StepOutput, TransformationEntry, TransformationHistory,
+};
+use crate::input::{self, EnigoBackend};
+use crate::models;
+use crate::providers::{build_provider, TransformProvider};
+use crate::review::{self, ReviewDecision, ReviewPayload};
+use crate::rich_text;
+use crate::settings::{
+    ChunkingSettings, Credential, PromptDefinition, ProviderSettings, RetryPolicy, TimeoutSettings,
+};
+use crate::template::{self, TemplateContext};
+use crate::transform::{
+    transform_candidates, transform_text, transform_text_streaming, CandidatesOutput,
+    TransformOutput,
+};
+
+/// How long to wait for the focused app to put its selection on the clipboard
+const SELECTION_COPY_TIMEOUT: Duration = Duration::from_millis(800);
+/// Time for focus to go back to the app once the review window closes
+const FOCUS_RETURN_DELAY: Duration = Duration::from_millis(300);
+
+/// Payload of the `transform-progress` event emitted while a response streams in
+#[derive(Debug, Clone, Serialize)]
+pub struct TransformProgress {
+    pub tone_name: String,
+    /// Zero-based index of the pipeline step being streamed
+    pub step: usize,
+    pub total_steps: usize,
+    pub partial_text: String,
 }
 
-// High-level function that handles clipboard transformation AND history tracking
-#[tauri::command]
-pub async fn transform_clipboard(
-    handle: tauri::AppHandle,
+// Renders the tone's prompt template. A template that inlines `{{text}}` is sent
+// as the whole user message; otherwise it stays the system prompt for the text.
+fn render_prompt(
+    definition: &PromptDefinition,
+    text: &str,
+    variables: &HashMap<String, String>,
+    app_name: Option<&str>,
+) -> Result<(PromptDefinition, String), MiloError> {
+    let context = TemplateContext {
+        text,
+        variables,
+        app_name,
+    };
+    let rendered = template::render(&definition.prompt, &context).map_err(MiloError::Settings)?;
+
+    let mut prompt = definition.clone();
+    if template::uses_variable(&definition.prompt, "text") {
+        prompt.prompt = String::new();
+        Ok((prompt, rendered))
+    } else {
+        prompt.prompt = rendered;
+        if has_placeholders(text) {
+            prompt.prompt.push_str(PLACEHOLDER_INSTRUCTION);
+        }
+        Ok((prompt, text.to_string()))
+    }
+}
+
+/// One prompt of a tone, resolved from settings before the transform starts
+struct PreparedStep {
     prompt_key: String,
-) -> Result<(), String> {
-    // Get and clean clipboard content
-    let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
-    let original_text = clipboard
-        .get_text()
-        .map_err(|e| format!("Failed to get clipboard text: {}", e))?;
-    let cleaned_original = clean_text(&original_text);
+    prompt: PromptDefinition,
+    provider_settings: ProviderSettings,
+    /// Credential the prompt selects, whose key the provider uses
+    credential: Option<Credential>,
+    /// Longest chunk for the step's model, see `chunking::max_chunk_chars`
+    chunk_chars: usize,
+}
+
+/// Settings shared by every step of one transform run
+struct RunContext<'a, R: Runtime> {
+    handle: &'a AppHandle<R>,
+    tone_name: &'a str,
+    total_steps: usize,
+    variables: &'a HashMap<String, String>,
+    /// Frontmost app, looked up once per run when a prompt uses `{{app_name}}`
+    app_name: Option<&'a str>,
+    retry_policy: &'a RetryPolicy,
+    timeouts: &'a TimeoutSettings,
+    chunking: &'a ChunkingSettings,
+    streaming: bool,
+    /// Fired by `cancel_transformation` or a replacing request
+    cancel: &'a CancellationToken,
+    /// Replaces every step's configured provider, see `AppState::provider_override`
+    provider_override: Option<&'a Arc<dyn TransformProvider>>,
+}
+
+// The provider for a step, unless the whole run uses an override
+async fn step_provider<R: Runtime>(
+    context: &RunContext<'_, R>,
+    step: &PreparedStep,
+) -> Result<Arc<dyn TransformProvider>, MiloError> {
+    match context.provider_override {
+        Some(provider) => Ok(provider.clone()),
+        None => Ok(build_provider(
+            &step.provider_settings,
+            step.credential.as_ref(),
+            context.timeouts,
+        )
+        .await?
+        .into()),
+    }
+}
+
+// Runs a single prompt over `text`, streaming progress to the webview when enabled.
+// Text longer than one chunk is split and transformed without streaming.
+async fn run_step<R: Runtime>(
+    context: &RunContext<'_, R>,
+    step: &PreparedStep,
+    step_index: usize,
+    text: &str,
+) -> Result<TransformOutput, MiloError> {
+    let provider = step_provider(context, step).await?;
+    let chunks = chunking::split_into_chunks(text, step.chunk_chars);
+    if chunks.len() > 1 {
+        println!(
+            "Splitting {} characters into {} chunks",
+            text.chars().count(),
+            chunks.len()
+        );
+        return run_chunked_step(context, step, provider.as_ref(), &chunks).await;
+    }
+
+    let (prompt, request_text) =
+        render_prompt(&step.prompt, text, context.variables, context.app_name)?;
+    let retry_policy = context.retry_policy;
+    let cancel = context.cancel;
+
+    if !context.streaming {
+        return transform_text(
+            provider.as_ref(),
+            &request_text,
+            &prompt,
+            retry_policy,
+            cancel,
+        )
+        .await;
+    }
+
+    let progress_handle = context.handle.clone();
+    let tone_name = context.tone_name.to_string();
+    let total_steps = context.total_steps;
+    let on_progress = move |partial: &str| {
+        let _ = progress_handle.emit(
+            "transform-progress",
+            TransformProgress {
+                tone_name: tone_name.clone(),
+                step: step_index,
+                total_steps,
+                partial_text: partial.to_string(),
+            },
+        );
+    };
+
+    transform_text_streaming(
+        provider.as_ref(),
+        &request_text,
+        &prompt,
+        retry_policy,
+        cancel,
+        &on_progress,
+    )
+    .await
+}
+
+// Transforms the chunks concurrently and stitches the outputs back in their original order
+async fn run_chunked_step<R: Runtime>(
+    context: &RunContext<'_, R>,
+    step: &PreparedStep,
+    provider: &dyn TransformProvider,
+    chunks: &[Chunk],
+) -> Result<TransformOutput, MiloError> {
+    let total = chunks.len();
+    let outputs = stream::iter(chunks.iter().enumerate())
+        .map(|(index, chunk)| async move {
+            let (mut prompt, request_text) =
+                render_prompt(
+                    &step.prompt,
+                    &chunk.text,
+                    context.variables,
+                    context.app_name,
+                )?;
+            if !prompt.prompt.is_empty() {
+                prompt.prompt.push_str(&format!(
+                    "\n\nThis text is part {} of {} of a longer document; transform only this part and keep it consistent with the rest.",
+                    index + 1,
+                    total
+                ));
+            }
+            transform_text(
+                provider,
+                &request_text,
+                &prompt,
+                context.retry_policy,
+                context.cancel,
+            )
+            .await
+        })
+        .buffered(context.chunking.max_concurrency.max(1))
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let attempts = outputs.iter().map(|output| output.attempts).sum();
+    let texts = outputs
+        .into_iter()
+        .map(|output| output.text)
+        .collect::<Vec<_>>();
+    Ok(TransformOutput {
+        text: chunking::stitch(chunks, &texts),
+        attempts,
+    })
+}
+
+/// Output of one run through every step of a tone
+struct RunOutput {
+    /// Final text with protected code restored
+    text: String,
+    attempts: u32,
+    steps: Vec<StepOutput>,
+    /// Alternatives from the last step when its prompt asks for candidates
+    candidates: Vec<String>,
+}
+
+// Generates several rewrites with the tone's last prompt. Candidates need the whole
+// text in one request, so text long enough to be chunked gets a single result.
+async fn run_candidates_step<R: Runtime>(
+    context: &RunContext<'_, R>,
+    step: &PreparedStep,
+    step_index: usize,
+    text: &str,
+    n: u32,
+) -> Result<CandidatesOutput, MiloError> {
+    if chunking::split_into_chunks(text, step.chunk_chars).len() > 1 {
+        println!("Text is too long for candidates, generating a single rewrite");
+        let output = run_step(context, step, step_index, text).await?;
+        return Ok(CandidatesOutput {
+            texts: vec![output.text],
+            attempts: output.attempts,
+        });
+    }
 
-    // Get the state and prompt
+    let provider = step_provider(context, step).await?;
+    let (prompt, request_text) =
+        render_prompt(&step.prompt, text, context.variables, context.app_name)?;
+    transform_candidates(
+        provider.as_ref(),
+        &request_text,
+        &prompt,
+        context.retry_policy,
+        context.cancel,
+        n,
+    )
+    .await
+}
+
+// Runs every step of the tone, each transforming the previous step's output
+async fn run_steps<R: Runtime>(
+    context: &RunContext<'_, R>,
+    steps: &[PreparedStep],
+    protected: &ProtectedText,
+) -> Result<RunOutput, MiloError> {
+    let mut text = protected.text.clone();
+    let mut attempts = 0;
+    let mut step_outputs = Vec::new();
+    let mut candidates = Vec::new();
+    for (index, step) in steps.iter().enumerate() {
+        let candidate_count = step.prompt.candidate_count();
+        let output = if index + 1 == steps.len() && candidate_count > 1 {
+            run_candidates_step(context, step, index, &text, candidate_count).await?
+        } else {
+            let output = run_step(context, step, index, &text).await?;
+            CandidatesOutput {
+                texts: vec![output.text],
+                attempts: output.attempts,
+            }
+        };
+
+        attempts += output.attempts;
+        candidates = output
+            .texts
+            .iter()
+            .map(|candidate| protected.restore(&clean_text(&restore_indentation(&text, candidate))))
+            .collect();
+        text = clean_text(&restore_indentation(&text, &output.texts[0]));
+        step_outputs.push(StepOutput {
+            tone_name: step.prompt_key.clone(),
+            output: protected.restore(&text),
+            attempts: output.attempts,
+        });
+    }
+
+    Ok(RunOutput {
+        text: protected.restore(&text),
+        attempts,
+        steps: step_outputs,
+        candidates: if candidates.len() > 1 {
+            candidates
+        } else {
+            Vec::new()
+        },
+    })
+}
+
+// Runs the tone and, in preview mode or when there are candidates to pick from,
+// re-runs it until the user accepts a result (Some) or rejects it (None)
+async fn run_until_accepted<R: Runtime>(
+    context: &RunContext<'_, R>,
+    steps: &[PreparedStep],
+    protected: &ProtectedText,
+    original: &str,
+    preview: bool,
+) -> Result<Option<RunOutput>, MiloError> {
+    let mut attempts = 0;
+    loop {
+        let mut run = run_steps(context, steps, protected).await?;
+        attempts += run.attempts;
+        run.attempts = attempts;
+        if !preview && run.candidates.is_empty() {
+            return Ok(Some(run));
+        }
+
+        let offered = if run.candidates.is_empty() {
+            vec![run.text.clone()]
+        } else {
+            run.candidates.clone()
+        };
+        let payload = ReviewPayload::new(context.tone_name, original, &offered);
+        let choice = review::request_review(context.handle, payload, context.cancel).await?;
+        match choice.decision {
+            ReviewDecision::Accept => {
+                run.text = offered[choice.candidate].clone();
+                if let Some(last_step) = run.steps.last_mut() {
+                    last_step.output = run.text.clone();
+                }
+                return Ok(Some(run));
+            }
+            ReviewDecision::Reject => return Ok(None),
+            ReviewDecision::Retry => println!("Retrying {} transform", context.tone_name),
+        }
+    }
+}
+
+// Reads the clipboard as text for the model. Rich text is converted to Markdown
+// so formatting survives; the flag says whether it should be written back as HTML.
+fn read_clipboard(
+    clipboard: &dyn ClipboardBackend,
+    plain_text_only: bool,
+) -> Result<(String, bool), MiloError> {
+    if !plain_text_only {
+        if let Ok(html) = clipboard.get_html() {
+            match rich_text::html_to_markdown(&html) {
+                Ok(markdown) if !markdown.trim().is_empty() => return Ok((markdown, true)),
+                Ok(_) => {}
+                Err(e) => println!("⚠️ Falling back to plain text: {}", e),
+            }
+        }
+    }
+
+    Ok((clipboard.get_text()?, false))
+}
+
+// Writes the result back, as HTML with a plain-text alternative when the input was rich text
+fn write_clipboard(
+    clipboard: &dyn ClipboardBackend,
+    text: &str,
+    is_html: bool,
+) -> Result<(), MiloError> {
+    if is_html {
+        let html = rich_text::markdown_to_html(text);
+        let plain = rich_text::markdown_to_plain_text(text);
+        clipboard.set_html(&html, Some(&plain))
+    } else {
+        clipboard.set_text(text)
+    }
+}
+
+// Copies the selection of the focused app and returns a snapshot of the clipboard
+// it replaced, so that can be put back once the result has been pasted
+async fn copy_selection(clipboard: &dyn ClipboardBackend) -> Result<ClipboardSnapshot, MiloError> {
+    let previous = ClipboardSnapshot::capture(clipboard);
+    clipboard.clear()?;
+    if let Err(e) = EnigoBackend::new().and_then(|mut backend| input::send_copy(&mut backend)) {
+        restore_snapshot(clipboard, &previous);
+        return Err(e);
+    }
+
+    let copied = input::wait_for_copy(|| clipboard.get_text().ok(), SELECTION_COPY_TIMEOUT).await;
+    if copied.is_none() {
+        restore_snapshot(clipboard, &previous);
+        return Err(MiloError::NoSelection);
+    }
+    Ok(previous)
+}
+
+// Pastes the result over the selection, then puts back what was on the clipboard before
+async fn paste_result(
+    clipboard: &dyn ClipboardBackend,
+    previous: &ClipboardSnapshot,
+) -> Result<(), MiloError> {
+    input::send_paste(&mut EnigoBackend::new()?)?;
+    tokio::time::sleep(PASTE_SETTLE_DELAY).await;
+    restore_snapshot(clipboard, previous);
+    Ok(())
+}
+
+fn restore_snapshot(clipboard: &dyn ClipboardBackend, snapshot: &ClipboardSnapshot) {
+    if let Err(e) = snapshot.restore(clipboard) {
+        println!("⚠️ {}", e);
+    }
+}
+
+// High-level function that handles clipboard transformation AND history tracking.
+// `prompt_key` names either a single prompt or a pipeline of prompts run in order.
+#[tauri::command]
+pub async fn transform_clipboard<R: Runtime>(
+    handle: AppHandle<R>,
+    prompt_key: String,
+) -> Result<(), MiloError> {
+    // Get the state and resolve every step of the tone
     let state = handle.state::<crate::AppState>();
     let settings = state.settings.lock().await;
-    let prompt = settings
-        .custom_prompts
-        .get(&prompt_key)
-        .ok_or_else(|| format!("Prompt not found for key: {}", prompt_key))?
-        .clone();
-
-    // Get LiteLLM API key and transform
-    let litellm_api_key = get_litellm_api_key()
-        .await
-        .map_err(|e| format!("Failed to get LiteLLM API key: {}", e))?;
+    // Context windows don't change, so a catalog past its TTL is still good here
+    let catalog = state
+        .model_catalog
+        .get(&CONFIG.litellm_base_url, Duration::MAX)
+        .unwrap_or_default();
+    let steps = settings
+        .steps_for_tone(&prompt_key)
+        .map_err(MiloError::Settings)?
+        .into_iter()
+        .map(|key| {
+            let prompt = settings.custom_prompts[&key].clone();
+            let provider_settings = settings.provider_for_prompt(&key);
+            // The catalog only lists the proxy's models; other providers may reuse a name
+            let context_window = prompt
+                .model
+                .as_deref()
+                .or(provider_settings.model.as_deref())
+                .filter(|_| provider_settings.uses_default_proxy())
+                .and_then(|model| models::context_window(&catalog, model));
+            PreparedStep {
+                chunk_chars: chunking::max_chunk_chars(
+                    settings.chunking.chunk_chars,
+                    context_window,
+                ),
+                prompt,
+                credential: provider_settings
+                    .credential
+                    .as_deref()
+                    .and_then(|id| settings.credential(id))
+                    .cloned(),
+                provider_settings,
+                prompt_key: key,
+            }
+        })
+        .collect::<Vec<_>>();
+    let is_pipeline = settings.pipelines.contains_key(&prompt_key);
+    let streaming = settings.is_streaming_enabled();
+    let retry_policy = settings.retry_policy.clone();
+    let timeouts = settings.timeouts.clone();
+    let variables = settings.template_variables.clone();
+    let chunking_settings = settings.chunking.clone();
+    let plain_text_only = settings.is_plain_text_only();
+    let concurrency_mode = settings.concurrency_mode;
+    let preview = settings.is_preview_enabled();
+    let replace_selection = settings.is_replace_selection_enabled();
+    let restore_settings = settings.clipboard_restore.clone();
 
     // Drop the lock before async operation
     drop(settings);
 
-    let transformed_text = transform_text(&cleaned_original, &prompt, &litellm_api_key).await?;
-    let cleaned_transformed = clean_text(&transformed_text);
+    // Held until this function returns so runs never race on the clipboard
+    let guard = state
+        .transforms
+        .acquire(concurrency_mode, &handle, &prompt_key)
+        .await?;
+
+    // Get and clean clipboard content
+    let clipboard = state.clipboard.as_ref();
+    // What was on the clipboard before, when it is going to be put back
+    let previous_clipboard = if replace_selection {
+        Some(copy_selection(clipboard).await?)
+    } else if restore_settings.enabled {
+        Some(ClipboardSnapshot::capture(clipboard))
+    } else {
+        None
+    };
+    let (original_text, is_html) = read_clipboard(clipboard, plain_text_only)?;
+    let cleaned_original = clean_text(&original_text);
+    // Code is never sent for rewriting; it goes back in once the transform is done
+    let protected = protect_code(&cleaned_original);
+
+    // osascript takes a moment, so it runs off the async workers and only once per run
+    let app_name = if steps
+        .iter()
+        .any(|step| template::uses_variable(&step.prompt.prompt, "app_name"))
+    {
+        tokio::task::spawn_blocking(crate::system::frontmost_app_name)
+            .await
+            .ok()
+            .flatten()
+    } else {
+        None
+    };
+
+    let context = RunContext {
+        handle: &handle,
+        tone_name: &prompt_key,
+        total_steps: steps.len(),
+        variables: &variables,
+        app_name: app_name.as_deref(),
+        retry_policy: &retry_policy,
+        timeouts: &timeouts,
+        chunking: &chunking_settings,
+        streaming,
+        cancel: guard.cancel_token(),
+        provider_override: state.provider_override.as_ref(),
+    };
+
+    // Splitting uses each step's own chunk size, so the smallest one is what fits
+    let chunk_chars = steps
+        .iter()
+        .map(|step| step.chunk_chars)
+        .min()
+        .unwrap_or(chunking_settings.chunk_chars);
+    let size_check = chunking::check_input_size(&protected.text, &chunking_settings, chunk_chars);
+    let result = match size_check {
+        Ok(()) => {
+            run_until_accepted(&context, &steps, &protected, &cleaned_original, preview).await
+        }
+        Err(e) => Err(e),
+    };
+    if replace_selection && !matches!(result, Ok(Some(_))) {
+        // Nothing gets pasted, so the copied selection shouldn't stay on the clipboard
+        if let Some(previous) = &previous_clipboard {
+            restore_snapshot(clipboard, previous);
+        }
+    }
+
+    let run = match result {
+        Ok(Some(run)) => run,
+        Ok(None) => {
+            println!("Transformation with {} tone rejected", prompt_key);
+            return Ok(());
+        }
+        Err(MiloError::Cancelled) => {
+            // Cancelling is the user's choice, so it is counted apart from failures
+            if let Err(e) = record_cancellation(&state.history_path) {
+                println!("Failed to record cancellation: {}", e);
+            }
+            println!("Transformation with {} tone cancelled", prompt_key);
+            return Err(MiloError::Cancelled);
+        }
+        Err(e) => return Err(e),
+    };
+    let cleaned_transformed = run.text;
+    let reviewed = preview || !run.candidates.is_empty();
 
     // Set transformed text back to clipboard
-    clipboard
-        .set_text(&cleaned_transformed)
-        .map_err(|e| format!("Failed to set clipboard text: {}", e))?;
+    write_clipboard(clipboard, &cleaned_transformed, is_html)?;
+    if let Some(previous) = previous_clipboard {
+        if replace_selection {
+            if reviewed {
+                tokio::time::sleep(FOCUS_RETURN_DELAY).await;
+            }
+            paste_result(clipboard, &previous).await?;
+        } else {
+            // A restore still pending from an earlier transform holds the contents
+            // from before that one, which are the ones to bring back
+            let previous = crate::clipboard::take_pending_snapshot(&handle).unwrap_or(previous);
+            let placed_text = clipboard.get_text().unwrap_or_default();
+            crate::clipboard::schedule_restore(&handle, previous, placed_text, &restore_settings);
+        }
+    }
 
     // Store in history (this is the key addition!)
-    add_transformation_to_history(prompt_key.clone(), cleaned_original, cleaned_transformed)?;
+    let mut entry =
+        TransformationEntry::new(prompt_key.clone(), cleaned_original, cleaned_transformed);
+    entry.attempts = run.attempts;
+    if is_pipeline {
+        entry.steps = run.steps;
+    }
+    entry.candidates = run.candidates;
+    record_entry(&state.history_path, entry).map_err(MiloError::History)?;
+    *state.undo_position.lock().unwrap() = 0;
 
     crate::notifications::show_notification(
         &handle,
@@ -65,12 +614,64 @@ pub async fn transform_clipboard(
     Ok(())
 }
 
+// Stops the transformation in progress; the clipboard is left untouched
+#[tauri::command]
+pub fn cancel_transformation(state: tauri::State<'_, crate::AppState>) -> Result<bool, String> {
+    let cancelled = state.transforms.cancel_current();
+    if cancelled {
+        println!("Transformation cancelled");
+    }
+    Ok(cancelled)
+}
+
+// Lets the webview pick up a transformation that started before it loaded
+#[tauri::command]
+pub fn is_transforming(state: tauri::State<'_, crate::AppState>) -> Result<bool, String> {
+    Ok(state.transforms.is_transforming())
+}
+
+// Puts the original text of a past transformation back on the clipboard. Each call
+// steps one entry further back; a new transformation starts again from the latest.
+#[tauri::command]
+pub fn undo_last_transformation(
+    state: tauri::State<'_, crate::AppState>,
+) -> Result<Option<TransformationEntry>, MiloError> {
+    let history = TransformationHistory::load(&state.history_path);
+    let mut position = state.undo_position.lock().unwrap();
+    let Some(entry) = history.entries.get(*position).cloned() else {
+        println!("Nothing left to undo");
+        return Ok(None);
+    };
+
+    state.clipboard.set_text(&entry.original_text)?;
+    *position += 1;
+
+    println!("Restored original text of {} transform", entry.tone_name);
+    Ok(Some(entry))
+}
+
+// Undo triggered from the tray or shortcut, reporting the outcome as a notification
+pub fn undo_with_notification(handle: &tauri::AppHandle) {
+    match undo_last_transformation(handle.state::<crate::AppState>()) {
+        Ok(Some(entry)) => crate::notifications::show_notification(
+            handle,
+            "Milo",
+            format!(
+                "Restored text from before the {} transform",
+                entry.tone_name
+            ),
+        ),
+        Ok(None) => crate::notifications::show_notification(handle, "Milo", "Nothing to undo"),
+        Err(e) => crate::notifications::show_error_notification(handle, &e),
+    }
+}
+
 // Function that reads tone from settings and performs transform with history
 #[tauri::command]
-pub async fn transform_clip_with_setting(
-    handle: tauri::AppHandle,
+pub async fn transform_clip_with_setting<R: Runtime>(
+    handle: AppHandle<R>,
     is_shortcut: bool,
-) -> Result<(), String> {
+) -> Result<(), MiloError> {
     // Get the state and selected tone
     let state = handle.state::<crate::AppState>();
     let settings = state.settings.lock().await;
@@ -83,10 +684,10 @@ pub async fn transform_clip_with_setting(
     let tone_key = settings
         .selected_tone
         .clone()
-        .ok_or_else(|| "No tone selected".to_string())?;
+        .ok_or_else(|| MiloError::Settings("No tone selected".to_string()))?;
 
     // Update selected tone in settings
-    settings.save()?;
+    settings.save().map_err(MiloError::Settings)?;
 
     // Drop the lock before transformation
     drop(settings);
@@ -94,3 +695,155 @@ pub async fn transform_clip_with_setting(
     // Perform transformation (which now includes history tracking)
     transform_clipboard(handle.clone(), tone_key).await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::MemoryClipboard;
+    use crate::providers::CompletionRequest;
+    use crate::settings::Settings;
+    use crate::state::AppState;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+    use tauri::test::MockRuntime;
+
+    // Fixes one typo the way a model would, remembering every request it gets
+    #[derive(Default)]
+    struct MockProvider {
+        requests: Mutex<Vec<CompletionRequest>>,
+        error: Option<MiloError>,
+    }
+
+    #[async_trait]
+    impl TransformProvider for MockProvider {
+        fn name(&self) -> &str {
+            "mock"
+        }
+
+        fn default_model(&self) -> &str {
+            "mock-model"
+        }
+
+        async fn complete(&self, request: CompletionRequest) -> Result<String, MiloError> {
+            self.requests.lock().unwrap().push(request.clone());
+            match &self.error {
+                Some(error) => Err(error.clone()),
+                None => Ok(request.text.replace("teh", "the")),
+            }
+        }
+    }
+
+    fn test_settings() -> Settings {
+        Settings {
+            streaming_enabled: Some(false),
+            ..Default::default()
+        }
+    }
+
+    fn mock_app(
+        settings: Settings,
+        clipboard: &Arc<MemoryClipboard>,
+        provider: &Arc<MockProvider>,
+    ) -> tauri::App<MockRuntime> {
+        let mut state = AppState::with_clipboard(settings, clipboard.clone());
+        state.provider_override = Some(provider.clone());
+        state.history_path = crate::history::test_history_path();
+        let app = tauri::test::mock_app();
+        app.manage(state);
+        app
+    }
+
+    #[tokio::test]
+    async fn test_transform_writes_result_to_clipboard() {
+        let clipboard = Arc::new(MemoryClipboard::with_text("I fixed teh bug.\n"));
+        let provider = Arc::new(MockProvider::default());
+        let app = mock_app(test_settings(), &clipboard, &provider);
+
+        transform_clipboard(app.handle().clone(), "Improve Writing".to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(clipboard.get_text().unwrap(), "I fixed the bug.");
+        let requests = provider.requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].text, "I fixed teh bug.");
+        assert!(requests[0].system_prompt.starts_with("Improve this text"));
+    }
+
+    #[tokio::test]
+    async fn test_code_never_reaches_provider() {
+        let clipboard = Arc::new(MemoryClipboard::with_text(
+            "Run `teh_tool` before teh release.",
+        ));
+        let provider = Arc::new(MockProvider::default());
+        let app = mock_app(test_settings(), &clipboard, &provider);
+
+        transform_clipboard(app.handle().clone(), "Improve Writing".to_string())
+            .await
+            .unwrap();
+
+        let sent = provider.requests.lock().unwrap()[0].text.clone();
+        assert!(!sent.contains("teh_tool"));
+        assert_eq!(
+            clipboard.get_text().unwrap(),
+            "Run `teh_tool` before the release."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rich_text_round_trip() {
+        let clipboard = Arc::new(MemoryClipboard::default());
+        clipboard
+            .set_html("<p>Fix <b>teh</b> typo</p>", Some("Fix teh typo"))
+            .unwrap();
+        let provider = Arc::new(MockProvider::default());
+        let app = mock_app(test_settings(), &clipboard, &provider);
+
+        transform_clipboard(app.handle().clone(), "Improve Writing".to_string())
+            .await
+            .unwrap();
+
+        assert!(provider.requests.lock().unwrap()[0]
+            .text
+            .contains("**teh**"));
+        assert!(clipboard
+            .get_html()
+            .unwrap()
+            .contains("<strong>the</strong>"));
+        assert_eq!(clipboard.get_text().unwrap(), "Fix the typo");
+    }
+
+    #[tokio::test]
+    async fn test_provider_error_leaves_clipboard_untouched() {
+        let clipboard = Arc::new(MemoryClipboard::with_text("Keep teh original."));
+        let provider = Arc::new(MockProvider {
+            error: Some(MiloError::Unauthorized),
+            ..Default::default()
+        });
+        let app = mock_app(test_settings(), &clipboard, &provider);
+
+        let result = transform_clipboard(app.handle().clone(), "Improve Writing".to_string()).await;
+
+        assert_eq!(result, Err(MiloError::Unauthorized));
+        assert_eq!(clipboard.get_text().unwrap(), "Keep teh original.");
+    }
+
+    #[tokio::test]
+    async fn test_oversized_input_is_refused() {
+        let clipboard = Arc::new(MemoryClipboard::with_text("Far too long for teh limit."));
+        let provider = Arc::new(MockProvider::default());
+        let settings = Settings {
+            chunking: ChunkingSettings {
+                max_input_chars: 10,
+                ..Default::default()
+            },
+            ..test_settings()
+        };
+        let app = mock_app(settings, &clipboard, &provider);
+
+        let result = transform_clipboard(app.handle().clone(), "Improve Writing".to_string()).await;
+
+        assert!(matches!(result, Err(MiloError::InputTooLarge { .. })));
+        assert!(provider.requests.lock().unwrap().is_empty());
+    }
+}
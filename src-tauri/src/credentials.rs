@@ -0,0 +1,162 @@
+use rand::Rng;
+use serde::Serialize;
+
+use crate::error::MiloError;
+use crate::key_status::{self, KeyStatus};
+use crate::providers::{credential_key, default_base_url, shared_client};
+use crate::secrets::{self, credential_secret_name, SystemSecrets};
+use crate::settings::{Credential, ProviderKind, Settings};
+use crate::state::AppState;
+
+/// A credential as listed in settings, with its key masked
+#[derive(Debug, Clone, Serialize)]
+pub struct CredentialInfo {
+    #[serde(flatten)]
+    pub credential: Credential,
+    /// Empty when the credential has no key, e.g. for a local server
+    pub key_preview: String,
+}
+
+impl CredentialInfo {
+    fn new(credential: Credential) -> Self {
+        let key = secrets::load_secret(&credential_secret_name(&credential.id))
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        Self {
+            credential,
+            key_preview: secrets::key_preview(&key),
+        }
+    }
+}
+
+// Applies `change` to a copy of the settings, which replaces them only once it
+// is valid and saved
+async fn update_settings(
+    state: &AppState,
+    change: impl FnOnce(&mut Settings) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut settings = state.settings.lock().await;
+    let mut updated = settings.clone();
+    change(&mut updated)?;
+    updated.validate()?;
+    updated.save()?;
+    *settings = updated;
+    Ok(())
+}
+
+fn find_credential<'a>(settings: &'a mut Settings, id: &str) -> Result<&'a mut Credential, String> {
+    settings
+        .credentials
+        .iter_mut()
+        .find(|credential| credential.id == id)
+        .ok_or_else(|| format!("Credential not found: {}", id))
+}
+
+#[tauri::command]
+pub async fn list_credentials(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<CredentialInfo>, String> {
+    let credentials = state.settings.lock().await.credentials.clone();
+    Ok(credentials.into_iter().map(CredentialInfo::new).collect())
+}
+
+#[tauri::command]
+pub async fn add_credential(
+    state: tauri::State<'_, AppState>,
+    name: String,
+    kind: ProviderKind,
+    base_url: Option<String>,
+    key: Option<String>,
+) -> Result<CredentialInfo, String> {
+    let credential = Credential {
+        id: format!("{:016x}", rand::thread_rng().gen::<u64>()),
+        name: name.trim().to_string(),
+        kind,
+        base_url: base_url.filter(|url| !url.trim().is_empty()),
+    };
+
+    let secret_name = credential_secret_name(&credential.id);
+    let key = key.filter(|key| !key.is_empty());
+    if let Some(key) = &key {
+        secrets::save_secret(&secret_name, key)?;
+    }
+    let added = credential.clone();
+    if let Err(e) = update_settings(&state, |settings| {
+        settings.credentials.push(added);
+        Ok(())
+    })
+    .await
+    {
+        // Don't leave a key behind that no credential points to
+        if key.is_some() {
+            let _ = secrets::delete_secret(&secret_name);
+        }
+        return Err(e);
+    }
+
+    println!("🔑 Added credential '{}'", credential.name);
+    Ok(CredentialInfo::new(credential))
+}
+
+#[tauri::command]
+pub async fn rename_credential(
+    state: tauri::State<'_, AppState>,
+    id: String,
+    name: String,
+) -> Result<(), String> {
+    update_settings(&state, |settings| {
+        find_credential(settings, &id)?.name = name.trim().to_string();
+        Ok(())
+    })
+    .await
+}
+
+/// Deletes a credential and its key. One still selected by a prompt is kept,
+/// since the prompt would otherwise quietly switch to the default key.
+#[tauri::command]
+pub async fn delete_credential(
+    state: tauri::State<'_, AppState>,
+    id: String,
+) -> Result<(), String> {
+    update_settings(&state, |settings| {
+        if let Some((prompt, _)) = settings
+            .prompt_providers
+            .iter()
+            .find(|(_, provider)| provider.credential.as_deref() == Some(id.as_str()))
+        {
+            return Err(format!("Credential is still used by prompt '{}'", prompt));
+        }
+        find_credential(settings, &id)?;
+        settings
+            .credentials
+            .retain(|credential| credential.id != id);
+        Ok(())
+    })
+    .await?;
+    secrets::delete_secret(&credential_secret_name(&id))
+}
+
+/// Checks a credential's key against its endpoint
+#[tauri::command]
+pub async fn test_credential(
+    state: tauri::State<'_, AppState>,
+    id: String,
+) -> Result<KeyStatus, MiloError> {
+    let (credential, timeouts) = {
+        let settings = state.settings.lock().await;
+        let credential = settings
+            .credential(&id)
+            .cloned()
+            .ok_or_else(|| MiloError::Settings(format!("Credential not found: {}", id)))?;
+        (credential, settings.timeouts.clone())
+    };
+    let key = credential_key(&SystemSecrets, &credential)?;
+    let base_url = credential
+        .base_url
+        .clone()
+        .unwrap_or_else(|| default_base_url(credential.kind));
+
+    let http = shared_client(&timeouts)?;
+    key_status::check_provider(&http, credential.kind, &base_url, &key).await
+}
@@ -0,0 +1,260 @@
+use std::{fmt, time::Duration};
+
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+/// Errors surfaced by the transform pipeline.
+///
+/// Serialized to the frontend as `{ "code": "...", "message": "..." }` where
+/// `code` is stable and safe to match on.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MiloError {
+    RateLimited {
+        retry_after: Option<Duration>,
+    },
+    Unauthorized,
+    Forbidden,
+    QuotaExceeded,
+    /// 5xx from the provider or proxy
+    Server {
+        status: u16,
+        retry_after: Option<Duration>,
+    },
+    Network(String),
+    Timeout,
+    EmptyCompletion,
+    /// Clipboard text exceeds the configured size limit
+    InputTooLarge {
+        chars: usize,
+        limit: usize,
+    },
+    Clipboard(String),
+    /// Nothing was copied when replacing the selection
+    NoSelection,
+    /// Synthetic key events could not be sent
+    Input(String),
+    Settings(String),
+    History(String),
+    /// Another transformation is already running
+    Busy,
+    Cancelled,
+    /// Provider error that doesn't fit any of the above
+    Api(String),
+}
+
+impl MiloError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            MiloError::RateLimited { .. } => "rate_limited",
+            MiloError::Unauthorized => "unauthorized",
+            MiloError::Forbidden => "forbidden",
+            MiloError::QuotaExceeded => "quota_exceeded",
+            MiloError::Server { .. } => "server_error",
+            MiloError::Network(_) => "network",
+            MiloError::Timeout => "timeout",
+            MiloError::EmptyCompletion => "empty_completion",
+            MiloError::InputTooLarge { .. } => "input_too_large",
+            MiloError::Clipboard(_) => "clipboard",
+            MiloError::NoSelection => "no_selection",
+            MiloError::Input(_) => "input",
+            MiloError::Settings(_) => "settings",
+            MiloError::History(_) => "history",
+            MiloError::Busy => "busy",
+            MiloError::Cancelled => "cancelled",
+            MiloError::Api(_) => "api",
+        }
+    }
+
+    /// Maps a non-success HTTP status to an error
+    pub fn from_status(status: u16, body: &str) -> Self {
+        match status {
+            401 => MiloError::Unauthorized,
+            402 => MiloError::QuotaExceeded,
+            403 => MiloError::Forbidden,
+            408 => MiloError::Timeout,
+            429 if is_quota_message(body) => MiloError::QuotaExceeded,
+            429 => MiloError::RateLimited { retry_after: None },
+            500..=599 => MiloError::Server {
+                status,
+                retry_after: None,
+            },
+            _ => MiloError::Api(format!("{} {}", status, body)),
+        }
+    }
+
+    /// Attaches a server-provided `Retry-After` delay to retryable errors
+    pub fn with_retry_after(self, delay: Option<Duration>) -> Self {
+        match self {
+            MiloError::RateLimited { .. } => MiloError::RateLimited { retry_after: delay },
+            MiloError::Server { status, .. } => MiloError::Server {
+                status,
+                retry_after: delay,
+            },
+            other => other,
+        }
+    }
+
+    /// Whether retrying the same request could succeed. Auth and quota
+    /// errors never resolve on their own, so they are not retried.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            MiloError::RateLimited { .. }
+                | MiloError::Server { .. }
+                | MiloError::Network(_)
+                | MiloError::Timeout
+        )
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            MiloError::RateLimited { retry_after } | MiloError::Server { retry_after, .. } => {
+                *retry_after
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Parses a `Retry-After` header value, given either in seconds or as an HTTP date
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(delay.to_std().unwrap_or(Duration::ZERO))
+}
+
+fn is_quota_message(message: &str) -> bool {
+    let message = message.to_lowercase();
+    message.contains("insufficient_quota")
+        || message.contains("quota")
+        || message.contains("budget")
+        || message.contains("billing")
+}
+
+impl fmt::Display for MiloError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MiloError::RateLimited { .. } => {
+                write!(
+                    f,
+                    "Rate limit exceeded - please top up your account balance"
+                )
+            }
+            MiloError::Unauthorized => {
+                write!(f, "Invalid API key - please check your credentials")
+            }
+            MiloError::Forbidden => {
+                write!(f, "API access forbidden - please check your account status")
+            }
+            MiloError::QuotaExceeded => {
+                write!(f, "Quota exceeded - please top up your account balance")
+            }
+            MiloError::Server { status, .. } => write!(f, "Server error ({})", status),
+            MiloError::Network(e) => write!(f, "Network error: {}", e),
+            MiloError::Timeout => write!(f, "Request timed out"),
+            MiloError::EmptyCompletion => write!(f, "No completion choices returned from API"),
+            MiloError::InputTooLarge { chars, limit } => write!(
+                f,
+                "Text is too long ({} characters, limit is {})",
+                chars, limit
+            ),
+            MiloError::Clipboard(e) => write!(f, "Clipboard error: {}", e),
+            MiloError::NoSelection => write!(f, "No text is selected"),
+            MiloError::Input(e) => write!(f, "Input error: {}", e),
+            MiloError::Settings(e) => write!(f, "Settings error: {}", e),
+            MiloError::History(e) => write!(f, "History error: {}", e),
+            MiloError::Busy => write!(f, "Another transformation is already running"),
+            MiloError::Cancelled => write!(f, "Transformation cancelled"),
+            MiloError::Api(e) => write!(f, "API error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MiloError {}
+
+impl Serialize for MiloError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("MiloError", 2)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+impl From<MiloError> for String {
+    fn from(error: MiloError) -> Self {
+        error.to_string()
+    }
+}
+
+impl From<reqwest::Error> for MiloError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            MiloError::Timeout
+        } else if let Some(status) = error.status() {
+            MiloError::from_status(status.as_u16(), &error.to_string())
+        } else if error.is_connect() || error.is_request() {
+            MiloError::Network(error.to_string())
+        } else if error.is_decode() {
+            MiloError::Api(format!("Failed to parse response: {}", error))
+        } else {
+            MiloError::Api(error.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_status() {
+        assert_eq!(MiloError::from_status(401, ""), MiloError::Unauthorized);
+        assert_eq!(MiloError::from_status(403, ""), MiloError::Forbidden);
+        assert_eq!(
+            MiloError::from_status(429, "slow down"),
+            MiloError::RateLimited { retry_after: None }
+        );
+        assert_eq!(
+            MiloError::from_status(429, "Budget has been exceeded"),
+            MiloError::QuotaExceeded
+        );
+        assert_eq!(
+            MiloError::from_status(418, "teapot"),
+            MiloError::Api("418 teapot".to_string())
+        );
+    }
+
+    #[test]
+    fn test_retryable_errors() {
+        assert!(MiloError::from_status(503, "").is_retryable());
+        assert!(MiloError::from_status(429, "").is_retryable());
+        assert!(MiloError::Timeout.is_retryable());
+        assert!(!MiloError::from_status(401, "").is_retryable());
+        assert!(!MiloError::from_status(429, "insufficient_quota").is_retryable());
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("3"), Some(Duration::from_secs(3)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn test_serializes_with_stable_code() {
+        let value = serde_json::to_value(MiloError::RateLimited { retry_after: None }).unwrap();
+        assert_eq!(value["code"], "rate_limited");
+        assert!(value["message"].as_str().unwrap().contains("Rate limit"));
+
+        let value = serde_json::to_value(MiloError::Clipboard("busy".to_string())).unwrap();
+        assert_eq!(value["code"], "clipboard");
+    }
+}
@@ -0,0 +1,481 @@
+/// Marks where protected code was lifted out of the text sent to the model
+const PLACEHOLDER_OPEN: &str = "⟦code-";
+const PLACEHOLDER_CLOSE: &str = "⟧";
+
+/// Appended to the system prompt when the text contains protected code
+pub const PLACEHOLDER_INSTRUCTION: &str =
+    "\n\nMarkers like ⟦code-0⟧ stand for code; keep every marker exactly as it is and in place.";
+
+/// Normalizes clipboard text without touching its structure: line endings
+/// become `\n`, trailing whitespace is dropped outside fenced code and
+/// surrounding blank lines are removed. Leading whitespace is always kept so
+/// nested lists, YAML and code keep their indentation.
+pub fn clean_text(text: &str) -> String {
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    let mut lines = Vec::new();
+    let mut fence: Option<Fence> = None;
+
+    for line in text.split('\n') {
+        match &fence {
+            Some(open) => {
+                if open.is_closed_by(line) {
+                    fence = None;
+                    lines.push(line.trim_end());
+                } else {
+                    lines.push(line);
+                }
+            }
+            None => {
+                fence = Fence::open(line);
+                lines.push(line.trim_end());
+            }
+        }
+    }
+
+    let first = lines.iter().position(|line| !line.trim().is_empty());
+    let last = lines.iter().rposition(|line| !line.trim().is_empty());
+    match (first, last) {
+        (Some(first), Some(last)) => lines[first..=last].join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Text with its code blocks and inline code spans swapped for
+/// placeholders, so code is never sent to the model for rewriting
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProtectedText {
+    pub text: String,
+    code: Vec<String>,
+}
+
+impl ProtectedText {
+    /// Puts the original code back in place of the placeholders in `output`
+    pub fn restore(&self, output: &str) -> String {
+        let mut restored = output.to_string();
+        for (index, code) in self.code.iter().enumerate() {
+            let marker = placeholder(index);
+            if restored.contains(&marker) {
+                restored = restored.replace(&marker, code);
+            } else {
+                println!("⚠️ Model dropped protected code block {}", index);
+            }
+        }
+        restored
+    }
+}
+
+/// Lifts fenced code blocks, indented code blocks and inline code spans out
+/// of `text`. Indented code follows the CommonMark rules: it can't interrupt a
+/// paragraph and is measured from the content column of its list item.
+pub fn protect_code(text: &str) -> ProtectedText {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let mut code = Vec::new();
+    let mut output: Vec<String> = Vec::new();
+    let mut context = BlockContext::default();
+    let mut index = 0;
+
+    while index < lines.len() {
+        let line = lines[index];
+        let block = match context.indented_code_end(&lines, index) {
+            Some(end) => Some((indent_of(line).to_string(), end)),
+            None => Fence::open(line).map(|fence| {
+                // An unclosed fence runs to the end of the text
+                let end = lines[index + 1..]
+                    .iter()
+                    .position(|line| fence.is_closed_by(line))
+                    .map_or(lines.len(), |offset| index + offset + 2);
+                (fence.indent, end)
+            }),
+        };
+
+        match block {
+            Some((indent, end)) => {
+                output.push(format!("{}{}", indent, placeholder(code.len())));
+                code.push(lines[index..end].join("\n")[indent.len()..].to_string());
+                context.in_paragraph = false;
+                index = end;
+            }
+            None => {
+                context.track(line);
+                output.push(protect_code_spans(line, &mut code));
+                index += 1;
+            }
+        }
+    }
+
+    ProtectedText {
+        text: output.join("\n"),
+        code,
+    }
+}
+
+/// Puts back leading whitespace the model stripped. Lines are matched one to
+/// one when the line count is unchanged, otherwise list items are matched in order.
+pub fn restore_indentation(input: &str, output: &str) -> String {
+    let input_lines: Vec<&str> = input.lines().collect();
+    let output_lines: Vec<&str> = output.lines().collect();
+
+    let indents: Vec<Option<&str>> = if input_lines.len() == output_lines.len() {
+        input_lines
+            .iter()
+            .map(|line| Some(indent_of(line)))
+            .collect()
+    } else {
+        let input_items: Vec<&str> = input_lines
+            .iter()
+            .filter(|line| is_list_item(line))
+            .map(|line| indent_of(line))
+            .collect();
+        let output_items = output_lines
+            .iter()
+            .filter(|line| is_list_item(line))
+            .count();
+        if input_items.len() != output_items {
+            return output.to_string();
+        }
+
+        let mut items = input_items.into_iter();
+        output_lines
+            .iter()
+            .map(|line| {
+                if is_list_item(line) {
+                    items.next()
+                } else {
+                    None
+                }
+            })
+            .collect()
+    };
+
+    output_lines
+        .iter()
+        .zip(indents)
+        .map(|(line, indent)| match indent {
+            Some(indent) if !line.is_empty() && indent_of(line).is_empty() => {
+                format!("{}{}", indent, line)
+            }
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Whether `text` still contains code placeholders from `protect_code`
+pub fn has_placeholders(text: &str) -> bool {
+    text.contains(PLACEHOLDER_OPEN)
+}
+
+fn placeholder(index: usize) -> String {
+    format!("{}{}{}", PLACEHOLDER_OPEN, index, PLACEHOLDER_CLOSE)
+}
+
+fn indent_of(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+// Bullet ("- ", "* ", "+ ") or ordered ("1. ", "2) ") list item
+fn is_list_item(line: &str) -> bool {
+    let line = line.trim_start();
+    if let Some(rest) = line.strip_prefix(['-', '*', '+']) {
+        return rest.starts_with(' ');
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    digits > 0 && {
+        let rest = &line[digits..];
+        rest.starts_with(". ") || rest.starts_with(") ")
+    }
+}
+
+// Indentation width in columns, with tabs stopping at multiples of 4
+fn columns(indent: &str) -> usize {
+    indent.chars().fold(0, |column, c| match c {
+        '\t' => column + 4 - column % 4,
+        _ => column + 1,
+    })
+}
+
+// Column where the content of the list item on `line` starts
+fn list_content_offset(line: &str) -> Option<usize> {
+    if !is_list_item(line) {
+        return None;
+    }
+    let indent = indent_of(line);
+    let rest = &line[indent.len()..];
+    let marker = rest.find(' ')?;
+    let spaces = rest[marker..].chars().take_while(|c| *c == ' ').count();
+    // Content 5+ spaces past the marker is code, which starts one space after it
+    let spaces = if spaces > 4 || rest[marker..].trim().is_empty() {
+        1
+    } else {
+        spaces
+    };
+    Some(columns(indent) + marker + spaces)
+}
+
+// ATX heading or thematic break; neither continues into the next line
+fn is_single_line_block(line: &str) -> bool {
+    let line = line.trim();
+    let hashes = line.chars().take_while(|c| *c == '#').count();
+    if (1..=6).contains(&hashes) && (line[hashes..].is_empty() || line[hashes..].starts_with(' ')) {
+        return true;
+    }
+    let marks: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3
+        && ['-', '*', '_']
+            .iter()
+            .any(|mark| marks.chars().all(|c| c == *mark))
+}
+
+/// What the lines so far leave open, as far as indented code cares
+#[derive(Default)]
+struct BlockContext {
+    /// Content columns of the open list items, innermost last
+    list_offsets: Vec<usize>,
+    /// Whether the previous line is paragraph text, which indented code can't interrupt
+    in_paragraph: bool,
+}
+
+impl BlockContext {
+    // Exclusive end of the indented code block starting at `index`, if one does
+    fn indented_code_end(&mut self, lines: &[&str], index: usize) -> Option<usize> {
+        let line = lines[index];
+        if self.in_paragraph || line.trim().is_empty() {
+            return None;
+        }
+        self.close_lists(columns(indent_of(line)));
+        let code_column = self.list_offsets.last().copied().unwrap_or(0) + 4;
+        if columns(indent_of(line)) < code_column {
+            return None;
+        }
+
+        // Blank lines inside the block belong to it; trailing ones don't
+        let mut end = index + 1;
+        for (offset, line) in lines[index + 1..].iter().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if columns(indent_of(line)) < code_column {
+                break;
+            }
+            end = index + offset + 2;
+        }
+        Some(end)
+    }
+
+    fn track(&mut self, line: &str) {
+        if line.trim().is_empty() {
+            self.in_paragraph = false;
+            return;
+        }
+
+        let indent = columns(indent_of(line));
+        match list_content_offset(line) {
+            Some(offset) => {
+                self.close_lists(indent);
+                self.list_offsets.push(offset);
+                // The item's own text opens a paragraph; an empty item doesn't
+                let item = line.trim_start();
+                self.in_paragraph = !item[item.find(' ').unwrap_or(0)..].trim().is_empty();
+            }
+            None => {
+                // A paragraph's lazy continuation lines stay in its list item
+                if !self.in_paragraph {
+                    self.close_lists(indent);
+                }
+                self.in_paragraph = !is_single_line_block(line);
+            }
+        }
+    }
+
+    // Ends the list items that a line indented `indent` columns falls outside of
+    fn close_lists(&mut self, indent: usize) {
+        while self
+            .list_offsets
+            .last()
+            .is_some_and(|offset| indent < *offset)
+        {
+            self.list_offsets.pop();
+        }
+    }
+}
+
+/// An open ``` or ~~~ code fence
+struct Fence {
+    indent: String,
+    marker: char,
+    len: usize,
+}
+
+impl Fence {
+    fn open(line: &str) -> Option<Fence> {
+        let rest = line.trim_start();
+        let marker = rest.chars().next().filter(|c| *c == '`' || *c == '~')?;
+        let len = rest.chars().take_while(|c| *c == marker).count();
+        // Backtick fences can't have backticks in their info string
+        let info = &rest[len..];
+        if len < 3 || (marker == '`' && info.contains('`')) {
+            return None;
+        }
+        Some(Fence {
+            indent: indent_of(line).to_string(),
+            marker,
+            len,
+        })
+    }
+
+    fn is_closed_by(&self, line: &str) -> bool {
+        let rest = line.trim();
+        rest.chars().count() >= self.len && rest.chars().all(|c| c == self.marker)
+    }
+}
+
+// Swaps `inline code` spans on one line for placeholders
+fn protect_code_spans(line: &str, code: &mut Vec<String>) -> String {
+    let mut output = String::with_capacity(line.len());
+    let mut rest = line;
+
+    while let Some(start) = rest.find('`') {
+        let ticks = rest[start..].chars().take_while(|c| *c == '`').count();
+        let after_open = &rest[start + ticks..];
+        let Some(end) = find_closing_ticks(after_open, ticks) else {
+            // No matching run: the backticks are literal
+            output.push_str(&rest[..start + ticks]);
+            rest = after_open;
+            continue;
+        };
+
+        output.push_str(&rest[..start]);
+        output.push_str(&placeholder(code.len()));
+        code.push(rest[start..start + ticks + end + ticks].to_string());
+        rest = &after_open[end + ticks..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+// Byte offset of the next run of exactly `ticks` backticks
+fn find_closing_ticks(text: &str, ticks: usize) -> Option<usize> {
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find('`') {
+        let start = offset + start;
+        let run = text[start..].chars().take_while(|c| *c == '`').count();
+        if run == ticks {
+            return Some(start);
+        }
+        offset = start + run;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_text_keeps_markdown_indentation() {
+        let text = "\r\n# Title  \r\n\r\n- item  \r\n  - nested\r\n    - deeper\r\n\r\n";
+        assert_eq!(
+            clean_text(text),
+            "# Title\n\n- item\n  - nested\n    - deeper"
+        );
+    }
+
+    #[test]
+    fn test_clean_text_keeps_yaml_and_python() {
+        let yaml = "services:\n  web:\n    image: nginx   \n    ports:\n      - \"80:80\"";
+        assert_eq!(
+            clean_text(yaml),
+            "services:\n  web:\n    image: nginx\n    ports:\n      - \"80:80\""
+        );
+
+        let python =
+            "def greet(name):\n    if name:\n        return f\"Hi {name}\"\n\n    return None\n";
+        assert_eq!(clean_text(python), python.trim_end());
+    }
+
+    #[test]
+    fn test_fenced_and_inline_code_are_protected() {
+        let text = "Run `cargo build` first:\n\n```rust\nfn main() {\n    println!(\"hi\");\n}\n```\n\nThen ship.";
+        let protected = protect_code(text);
+
+        assert_eq!(
+            protected.text,
+            "Run ⟦code-0⟧ first:\n\n⟦code-1⟧\n\nThen ship."
+        );
+        assert!(!protected.text.contains("println"));
+
+        let output = "First run ⟦code-0⟧:\n\n⟦code-1⟧\n\nThen deploy.";
+        assert_eq!(
+            protected.restore(output),
+            "First run `cargo build`:\n\n```rust\nfn main() {\n    println!(\"hi\");\n}\n```\n\nThen deploy."
+        );
+    }
+
+    #[test]
+    fn test_fence_inside_list_keeps_its_indent() {
+        let text = "1. Install:\n   ```sh\n   npm i\n   ```\n2. Done";
+        let protected = protect_code(text);
+
+        assert_eq!(protected.text, "1. Install:\n   ⟦code-0⟧\n2. Done");
+        assert_eq!(protected.restore(&protected.text), text);
+    }
+
+    #[test]
+    fn test_indented_code_is_protected() {
+        let text = "Example:\n\n    let x = 1;\n\n\tx + 1\n\nDone.";
+        let protected = protect_code(text);
+
+        assert_eq!(protected.text, "Example:\n\n    ⟦code-0⟧\n\nDone.");
+        assert_eq!(protected.restore(&protected.text), text);
+    }
+
+    #[test]
+    fn test_indented_code_follows_list_items() {
+        // Four spaces only continue the item's text; eight make code inside it
+        let text = "- step one\n\n    still step one\n\n        make build\n- step two";
+        let protected = protect_code(text);
+
+        assert_eq!(
+            protected.text,
+            "- step one\n\n    still step one\n\n        ⟦code-0⟧\n- step two"
+        );
+        assert_eq!(protected.restore(&protected.text), text);
+
+        // Indented lines can't interrupt a paragraph
+        let text = "Some text\n    that wraps\n\n# Heading\n    code";
+        assert_eq!(
+            protect_code(text).text,
+            "Some text\n    that wraps\n\n# Heading\n    ⟦code-0⟧"
+        );
+    }
+
+    #[test]
+    fn test_yaml_and_python_are_not_protected() {
+        let yaml = "steps:\n  - name: build\n    run: make";
+        assert_eq!(protect_code(yaml).text, yaml);
+
+        let python = "def add(a, b):\n    return a + b";
+        assert_eq!(protect_code(python).text, python);
+    }
+
+    #[test]
+    fn test_restore_indentation_line_for_line() {
+        let input = "def add(a, b):\n    # sum\n    return a + b";
+        let output = "def add(a, b):\n# Add both numbers\nreturn a + b";
+        assert_eq!(
+            restore_indentation(input, output),
+            "def add(a, b):\n    # Add both numbers\n    return a + b"
+        );
+    }
+
+    #[test]
+    fn test_restore_indentation_matches_list_items() {
+        let input = "Todo:\n- buy milk\n  - whole\n  - oat\n- call mom";
+        let output = "My todo list:\n\n- Buy milk\n- Whole\n- Oat\n- Call mom";
+        assert_eq!(
+            restore_indentation(input, output),
+            "My todo list:\n\n- Buy milk\n  - Whole\n  - Oat\n- Call mom"
+        );
+    }
+}
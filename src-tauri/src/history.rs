@@ -3,9 +3,13 @@ use chrono::{DateTime, NaiveDate, Utc};
 use dirs::config_dir;
 use jieba_rs::Jieba;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fs, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct TransformationEntry {
     pub tone_name: String,
     pub original_text: String,
@@ -15,6 +19,49 @@ pub struct TransformationEntry {
     pub sentence_count: usize,
     pub added_count: usize,
     pub removed_count: usize,
+    /// Provider calls made, including retries
+    #[serde(default = "default_attempts")]
+    pub attempts: u32,
+    /// Intermediate outputs when the tone is a pipeline, in run order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<StepOutput>,
+    /// Every rewrite offered when the tone generates candidates, including the chosen one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub candidates: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StepOutput {
+    pub tone_name: String,
+    pub output: String,
+    pub attempts: u32,
+}
+
+fn default_attempts() -> u32 {
+    1
+}
+
+impl TransformationEntry {
+    /// Builds an entry for a transformation made now, computing the diff stats
+    pub fn new(tone_name: String, original: String, transformed: String) -> Self {
+        let diff = compute_word_diff(&original, &transformed);
+        let word_count = diff.added_count + diff.removed_count;
+        let sentence_count = count_sentences(&transformed);
+
+        Self {
+            tone_name,
+            original_text: original,
+            transformed_text: transformed,
+            timestamp: Utc::now(),
+            word_count,
+            sentence_count,
+            added_count: diff.added_count,
+            removed_count: diff.removed_count,
+            attempts: 1,
+            steps: Vec::new(),
+            candidates: Vec::new(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -38,6 +85,9 @@ pub struct DayStats {
     pub transformation_count: usize,
     pub word_count: usize,
     pub sentence_count: usize,
+    /// Transformations the user cancelled; not part of `transformation_count`
+    #[serde(default)]
+    pub cancelled_count: usize,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -58,16 +108,16 @@ impl Default for TransformationHistory {
 }
 
 impl TransformationHistory {
-    pub fn load() -> Self {
-        fs::read_to_string(history_file_path())
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
             .ok()
             .and_then(|contents| serde_json::from_str(&contents).ok())
             .unwrap_or_default()
     }
 
-    pub fn save(&self) -> Result<(), String> {
+    pub fn save(&self, path: &Path) -> Result<(), String> {
         let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
-        fs::write(history_file_path(), json).map_err(|e| e.to_string())
+        fs::write(path, json).map_err(|e| e.to_string())
     }
 
     pub fn add_entry(&mut self, entry: TransformationEntry) {
@@ -82,21 +132,25 @@ impl TransformationHistory {
         }
 
         // Update daily stats
-        let date = entry.timestamp.date_naive();
-        let date_key = date.format("%Y-%m-%d").to_string();
-
-        let day_stats = self.daily_stats.entry(date_key).or_insert(DayStats {
-            date,
-            transformation_count: 0,
-            word_count: 0,
-            sentence_count: 0,
-        });
-
+        let day_stats = self.day_stats_mut(entry.timestamp.date_naive());
         day_stats.transformation_count += 1;
         day_stats.word_count += entry.word_count;
         day_stats.sentence_count += entry.sentence_count;
     }
 
+    /// Counts a cancelled transformation without adding an entry
+    pub fn add_cancellation(&mut self, timestamp: DateTime<Utc>) {
+        self.day_stats_mut(timestamp.date_naive()).cancelled_count += 1;
+    }
+
+    fn day_stats_mut(&mut self, date: NaiveDate) -> &mut DayStats {
+        let date_key = date.format("%Y-%m-%d").to_string();
+        self.daily_stats.entry(date_key).or_insert(DayStats {
+            date,
+            ..Default::default()
+        })
+    }
+
     pub fn get_recent_entries(&self, limit: usize) -> &[TransformationEntry] {
         let end = self.entries.len().min(limit);
         &self.entries[0..end]
@@ -114,6 +168,10 @@ impl TransformationHistory {
         self.entries.iter().map(|e| e.sentence_count).sum()
     }
 
+    pub fn get_total_cancelled(&self) -> usize {
+        self.daily_stats.values().map(|s| s.cancelled_count).sum()
+    }
+
     pub fn clear_history(&mut self) {
         self.entries.clear();
         self.daily_stats.clear();
@@ -128,6 +186,19 @@ pub fn history_file_path() -> PathBuf {
     path
 }
 
+/// A history file of its own in the temp directory, so tests never touch the user's history
+#[cfg(test)]
+pub fn test_history_path() -> PathBuf {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static NEXT_FILE: AtomicUsize = AtomicUsize::new(0);
+    std::env::temp_dir().join(format!(
+        "milo_test_history_{}_{}.json",
+        std::process::id(),
+        NEXT_FILE.fetch_add(1, Ordering::SeqCst)
+    ))
+}
+
 // Function to compute word-level diff between two texts
 pub fn compute_word_diff(original: &str, transformed: &str) -> TextDiff {
     // Helper function to tokenize text into words
@@ -256,26 +327,26 @@ fn compute_lcs(a: &[String], b: &[String]) -> Vec<String> {
     lcs
 }
 
+// Sentence-ending punctuation for different languages
+// Including both half-width and full-width (Chinese/Japanese) punctuation
+pub const SENTENCE_ENDINGS: [char; 12] = [
+    '.', '!', '?', // English half-width
+    '。', '！', '？', // Chinese/Japanese full-width
+    '…', '⋯', // Ellipsis
+    '‼', '⁇', '⁈', '⁉', // Special punctuation
+];
+
 // Function to count sentences in text, supporting multiple languages and punctuation types
 pub fn count_sentences(text: &str) -> usize {
     if text.trim().is_empty() {
         return 0;
     }
 
-    // Define sentence-ending punctuation for different languages
-    // Including both half-width and full-width (Chinese/Japanese) punctuation
-    let sentence_endings = [
-        '.', '!', '?', // English half-width
-        '。', '！', '？', // Chinese/Japanese full-width
-        '…', '⋯', // Ellipsis
-        '‼', '⁇', '⁈', '⁉', // Special punctuation
-    ];
-
     let mut sentence_count = 0;
     let chars: Vec<char> = text.chars().collect();
 
     for (i, &ch) in chars.iter().enumerate() {
-        if sentence_endings.contains(&ch) {
+        if SENTENCE_ENDINGS.contains(&ch) {
             sentence_count += 1;
 
             // Handle multiple consecutive punctuation (like "..." or "!!!")
@@ -284,7 +355,7 @@ pub fn count_sentences(text: &str) -> usize {
             while j < chars.len()
                 && (chars[j] == ch
                     || chars[j].is_whitespace()
-                    || sentence_endings.contains(&chars[j]))
+                    || SENTENCE_ENDINGS.contains(&chars[j]))
             {
                 j += 1;
             }
@@ -302,53 +373,63 @@ pub fn count_sentences(text: &str) -> usize {
 // Tauri Commands
 #[tauri::command]
 pub fn add_transformation_to_history(
+    state: tauri::State<'_, crate::AppState>,
     tone_name: String,
     original: String,
     transformed: String,
 ) -> Result<(), String> {
-    let mut history = TransformationHistory::load();
-
-    // Calculate diff data immediately
-    let diff = compute_word_diff(&original, &transformed);
-    let word_count = diff.added_count + diff.removed_count;
-    let sentence_count = count_sentences(&transformed);
-
-    let entry = TransformationEntry {
-        tone_name,
-        original_text: original,
-        transformed_text: transformed,
-        timestamp: Utc::now(),
-        word_count,
-        sentence_count,
-        added_count: diff.added_count,
-        removed_count: diff.removed_count,
-    };
+    record_entry(
+        &state.history_path,
+        TransformationEntry::new(tone_name, original, transformed),
+    )
+}
 
+// Appends a fully built entry to the history stored at `path`
+pub fn record_entry(path: &Path, entry: TransformationEntry) -> Result<(), String> {
+    let mut history = TransformationHistory::load(path);
     history.add_entry(entry);
-    history.save()?;
+    history.save(path)
+}
 
-    Ok(())
+// Notes a cancelled transformation in the daily stats
+pub fn record_cancellation(path: &Path) -> Result<(), String> {
+    let mut history = TransformationHistory::load(path);
+    history.add_cancellation(Utc::now());
+    history.save(path)
 }
 
 #[tauri::command]
 pub fn get_transformation_history(
+    state: tauri::State<'_, crate::AppState>,
     limit: Option<usize>,
 ) -> Result<Vec<TransformationEntry>, String> {
-    let history = TransformationHistory::load();
+    let history = TransformationHistory::load(&state.history_path);
     let limit = limit.unwrap_or(50);
     Ok(history.get_recent_entries(limit).to_vec())
 }
 
+// Undo walks the entries by position, so it starts over from the newest once they change
+fn reset_undo(state: &crate::AppState) {
+    *state.undo_position.lock().unwrap() = 0;
+}
+
 #[tauri::command]
-pub fn clear_transformation_history() -> Result<(), String> {
-    let mut history = TransformationHistory::load();
+pub fn clear_transformation_history(
+    state: tauri::State<'_, crate::AppState>,
+) -> Result<(), String> {
+    let mut history = TransformationHistory::load(&state.history_path);
     history.clear_history();
-    history.save()
+    history.save(&state.history_path)?;
+    reset_undo(&state);
+    Ok(())
 }
 
 #[tauri::command]
-pub fn delete_transformation_entry(index: usize) -> Result<(), String> {
-    let mut history = TransformationHistory::load();
+pub fn delete_transformation_entry(
+    state: tauri::State<'_, crate::AppState>,
+    index: usize,
+) -> Result<(), String> {
+    let mut history = TransformationHistory::load(&state.history_path);
 
     if index >= history.entries.len() {
         return Err("Entry index out of bounds".to_string());
@@ -368,30 +449,38 @@ pub fn delete_transformation_entry(index: usize) -> Result<(), String> {
             .sentence_count
             .saturating_sub(removed_entry.sentence_count);
 
-        // Remove the day stats if no transformations left for that day
-        if day_stats.transformation_count == 0 {
+        // Remove the day stats if nothing is left for that day
+        if day_stats.transformation_count == 0 && day_stats.cancelled_count == 0 {
             history.daily_stats.remove(&date_key);
         }
     }
 
-    history.save()
+    history.save(&state.history_path)?;
+    reset_undo(&state);
+    Ok(())
 }
 
 #[tauri::command]
-pub fn get_usage_stats() -> Result<serde_json::Value, String> {
-    let history = TransformationHistory::load();
+pub fn get_usage_stats(
+    state: tauri::State<'_, crate::AppState>,
+) -> Result<serde_json::Value, String> {
+    let history = TransformationHistory::load(&state.history_path);
 
     Ok(serde_json::json!({
         "total_transformations": history.get_total_transformations(),
         "total_words_transformed": history.get_total_words_transformed(),
         "total_sentences_transformed": history.get_total_sentences_transformed(),
+        "total_cancelled": history.get_total_cancelled(),
         "history_count": history.entries.len()
     }))
 }
 
 #[tauri::command]
-pub fn get_daily_stats(days: Option<usize>) -> Result<Vec<DayStats>, String> {
-    let history = TransformationHistory::load();
+pub fn get_daily_stats(
+    state: tauri::State<'_, crate::AppState>,
+    days: Option<usize>,
+) -> Result<Vec<DayStats>, String> {
+    let history = TransformationHistory::load(&state.history_path);
     let days = days.unwrap_or(7); // Default to 7 days
 
     let today = Utc::now().date_naive();
@@ -408,9 +497,7 @@ pub fn get_daily_stats(days: Option<usize>) -> Result<Vec<DayStats>, String> {
             .cloned()
             .unwrap_or(DayStats {
                 date,
-                transformation_count: 0,
-                word_count: 0,
-                sentence_count: 0,
+                ..Default::default()
             });
 
         stats.push(day_stats);
@@ -427,8 +514,8 @@ mod tests {
     use chrono::{TimeZone, Utc};
     use std::fs;
 
-    fn cleanup_test_files() {
-        let _ = fs::remove_file(history_file_path());
+    fn cleanup_test_files(path: &Path) {
+        let _ = fs::remove_file(path);
     }
 
     #[test]
@@ -442,6 +529,7 @@ mod tests {
             sentence_count: 1,
             added_count: 0,
             removed_count: 0,
+            ..Default::default()
         };
 
         assert_eq!(entry.tone_name, "Improve Writing");
@@ -450,8 +538,6 @@ mod tests {
 
     #[test]
     fn test_history_add_entry() {
-        cleanup_test_files();
-
         let mut history = TransformationHistory::default();
 
         let entry = TransformationEntry {
@@ -463,6 +549,7 @@ mod tests {
             sentence_count: 1,
             added_count: 0,
             removed_count: 0,
+            ..Default::default()
         };
 
         history.add_entry(entry);
@@ -477,14 +564,10 @@ mod tests {
         let day_stats = &history.daily_stats[date_key];
         assert_eq!(day_stats.transformation_count, 1);
         assert_eq!(day_stats.word_count, 1);
-
-        cleanup_test_files();
     }
 
     #[test]
     fn test_history_multiple_entries_same_day() {
-        cleanup_test_files();
-
         let mut history = TransformationHistory::default();
         let test_date = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
 
@@ -498,6 +581,7 @@ mod tests {
             sentence_count: 1,
             added_count: 0,
             removed_count: 0,
+            ..Default::default()
         };
         history.add_entry(entry1);
 
@@ -511,6 +595,7 @@ mod tests {
             sentence_count: 1,
             added_count: 0,
             removed_count: 0,
+            ..Default::default()
         };
         history.add_entry(entry2);
 
@@ -523,14 +608,10 @@ mod tests {
         let day_stats = &history.daily_stats[date_key];
         assert_eq!(day_stats.transformation_count, 2);
         assert_eq!(day_stats.word_count, 5);
-
-        cleanup_test_files();
     }
 
     #[test]
     fn test_history_max_entries_limit() {
-        cleanup_test_files();
-
         let mut history = TransformationHistory::default();
         history.max_entries = Some(3);
 
@@ -545,6 +626,7 @@ mod tests {
                 sentence_count: 1,
                 added_count: 0,
                 removed_count: 0,
+                ..Default::default()
             };
             history.add_entry(entry);
         }
@@ -556,14 +638,10 @@ mod tests {
         assert_eq!(history.entries[0].tone_name, "Tone 4");
         assert_eq!(history.entries[1].tone_name, "Tone 3");
         assert_eq!(history.entries[2].tone_name, "Tone 2");
-
-        cleanup_test_files();
     }
 
     #[test]
     fn test_get_recent_entries() {
-        cleanup_test_files();
-
         let mut history = TransformationHistory::default();
 
         // Add some entries
@@ -577,6 +655,7 @@ mod tests {
                 sentence_count: 1,
                 added_count: 0,
                 removed_count: 0,
+                ..Default::default()
             };
             history.add_entry(entry);
         }
@@ -584,14 +663,10 @@ mod tests {
         let recent = history.get_recent_entries(3);
         assert_eq!(recent.len(), 3);
         assert_eq!(recent[0].tone_name, "Tone 4"); // Most recent first
-
-        cleanup_test_files();
     }
 
     #[test]
     fn test_clear_history() {
-        cleanup_test_files();
-
         let mut history = TransformationHistory::default();
 
         // Add an entry
@@ -604,6 +679,7 @@ mod tests {
             sentence_count: 1,
             added_count: 0,
             removed_count: 0,
+            ..Default::default()
         };
         history.add_entry(entry);
 
@@ -616,14 +692,24 @@ mod tests {
         assert_eq!(history.daily_stats.len(), 0);
         assert_eq!(history.get_total_transformations(), 0);
         assert_eq!(history.get_total_words_transformed(), 0);
+    }
 
-        cleanup_test_files();
+    #[test]
+    fn test_cancellation_is_not_a_transformation() {
+        let mut history = TransformationHistory::default();
+        history.add_cancellation(Utc::now());
+
+        assert_eq!(history.entries.len(), 0);
+        assert_eq!(history.get_total_transformations(), 0);
+        assert_eq!(history.get_total_cancelled(), 1);
+
+        let day_stats = history.daily_stats.values().next().unwrap();
+        assert_eq!(day_stats.transformation_count, 0);
+        assert_eq!(day_stats.cancelled_count, 1);
     }
 
     #[test]
     fn test_daily_stats_generation() {
-        cleanup_test_files();
-
         let mut history = TransformationHistory::default();
 
         // Add entries on different days
@@ -639,6 +725,7 @@ mod tests {
             sentence_count: 1,
             added_count: 0,
             removed_count: 0,
+            ..Default::default()
         };
         history.add_entry(entry1);
 
@@ -651,6 +738,7 @@ mod tests {
             sentence_count: 1,
             added_count: 0,
             removed_count: 0,
+            ..Default::default()
         };
         history.add_entry(entry2);
 
@@ -663,13 +751,11 @@ mod tests {
         let day2_stats = &history.daily_stats["2024-01-16"];
         assert_eq!(day2_stats.transformation_count, 1);
         assert_eq!(day2_stats.word_count, 2);
-
-        cleanup_test_files();
     }
 
     #[test]
     fn test_save_and_load() {
-        cleanup_test_files();
+        let path = test_history_path();
 
         // Create and save history
         let mut history = TransformationHistory::default();
@@ -682,20 +768,21 @@ mod tests {
             sentence_count: 1,
             added_count: 0,
             removed_count: 0,
+            ..Default::default()
         };
         history.add_entry(entry);
 
         // Save to file
-        history.save().expect("Failed to save history");
+        history.save(&path).expect("Failed to save history");
 
         // Load from file
-        let loaded_history = TransformationHistory::load();
+        let loaded_history = TransformationHistory::load(&path);
 
         assert_eq!(loaded_history.entries.len(), 1);
         assert_eq!(loaded_history.entries[0].tone_name, "Test Save");
         assert_eq!(loaded_history.daily_stats.len(), 1);
 
-        cleanup_test_files();
+        cleanup_test_files(&path);
     }
 
     #[test]
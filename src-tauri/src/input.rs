@@ -0,0 +1,188 @@
+use std::time::Duration;
+
+use crate::error::MiloError;
+
+/// How often the clipboard is checked while waiting for a simulated copy
+const COPY_POLL_INTERVAL: Duration = Duration::from_millis(25);
+
+/// Keys pressed by the replace-selection mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Control,
+    /// Cmd on macOS, the Super/Windows key elsewhere
+    Meta,
+    Shift,
+    Alt,
+    Char(char),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Press,
+    Release,
+    Click,
+}
+
+/// Sends synthetic key events to the focused application
+pub trait InputBackend {
+    fn key(&mut self, key: Key, direction: Direction) -> Result<(), MiloError>;
+}
+
+/// Key events through enigo; on Linux this talks to the X11 server
+pub struct EnigoBackend {
+    enigo: enigo::Enigo,
+}
+
+impl EnigoBackend {
+    pub fn new() -> Result<Self, MiloError> {
+        let enigo = enigo::Enigo::new(&enigo::Settings::default())
+            .map_err(|e| MiloError::Input(format!("Failed to connect to input system: {}", e)))?;
+        Ok(Self { enigo })
+    }
+}
+
+impl InputBackend for EnigoBackend {
+    fn key(&mut self, key: Key, direction: Direction) -> Result<(), MiloError> {
+        use enigo::Keyboard;
+
+        let key = match key {
+            Key::Control => enigo::Key::Control,
+            Key::Meta => enigo::Key::Meta,
+            Key::Shift => enigo::Key::Shift,
+            Key::Alt => enigo::Key::Alt,
+            Key::Char(c) => enigo::Key::Unicode(c),
+        };
+        let direction = match direction {
+            Direction::Press => enigo::Direction::Press,
+            Direction::Release => enigo::Direction::Release,
+            Direction::Click => enigo::Direction::Click,
+        };
+        self.enigo
+            .key(key, direction)
+            .map_err(|e| MiloError::Input(format!("Failed to send key event: {}", e)))
+    }
+}
+
+// The modifier for copy and paste: Cmd on macOS, Ctrl everywhere else
+fn shortcut_modifier() -> Key {
+    if cfg!(target_os = "macos") {
+        Key::Meta
+    } else {
+        Key::Control
+    }
+}
+
+// Presses modifier+letter. The user may still be holding the keys of the global
+// shortcut, so those are released first or the target app would see them too.
+fn press_shortcut(backend: &mut impl InputBackend, letter: char) -> Result<(), MiloError> {
+    for key in [Key::Shift, Key::Alt, Key::Meta, Key::Control] {
+        backend.key(key, Direction::Release)?;
+    }
+
+    let modifier = shortcut_modifier();
+    backend.key(modifier, Direction::Press)?;
+    let result = backend.key(Key::Char(letter), Direction::Click);
+    // Release the modifier even when the letter failed so it doesn't stay stuck
+    backend.key(modifier, Direction::Release)?;
+    result
+}
+
+/// Copies the selection in the focused application
+pub fn send_copy(backend: &mut impl InputBackend) -> Result<(), MiloError> {
+    press_shortcut(backend, 'c')
+}
+
+/// Pastes the clipboard into the focused application
+pub fn send_paste(backend: &mut impl InputBackend) -> Result<(), MiloError> {
+    press_shortcut(backend, 'v')
+}
+
+/// Polls `read` until the copied selection shows up, giving up after `timeout`.
+/// Applications copy asynchronously, so the clipboard lags behind the key press.
+pub async fn wait_for_copy(
+    mut read: impl FnMut() -> Option<String>,
+    timeout: Duration,
+) -> Option<String> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        if let Some(text) = read().filter(|text| !text.is_empty()) {
+            return Some(text);
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return None;
+        }
+        tokio::time::sleep(COPY_POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct FakeInput {
+        events: Vec<(Key, Direction)>,
+        fail_on: Option<Key>,
+    }
+
+    impl InputBackend for FakeInput {
+        fn key(&mut self, key: Key, direction: Direction) -> Result<(), MiloError> {
+            if self.fail_on == Some(key) {
+                return Err(MiloError::Input("no display".to_string()));
+            }
+            self.events.push((key, direction));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_copy_releases_held_modifiers_first() {
+        let mut input = FakeInput::default();
+        send_copy(&mut input).unwrap();
+
+        let modifier = shortcut_modifier();
+        assert_eq!(
+            input.events,
+            vec![
+                (Key::Shift, Direction::Release),
+                (Key::Alt, Direction::Release),
+                (Key::Meta, Direction::Release),
+                (Key::Control, Direction::Release),
+                (modifier, Direction::Press),
+                (Key::Char('c'), Direction::Click),
+                (modifier, Direction::Release),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_paste_releases_modifier_when_letter_fails() {
+        let mut input = FakeInput {
+            fail_on: Some(Key::Char('v')),
+            ..Default::default()
+        };
+
+        assert!(send_paste(&mut input).is_err());
+        assert_eq!(
+            input.events.last(),
+            Some(&(shortcut_modifier(), Direction::Release))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_copy() {
+        let mut reads = 0;
+        let copied = wait_for_copy(
+            || {
+                reads += 1;
+                (reads >= 3).then(|| "selected".to_string())
+            },
+            Duration::from_secs(1),
+        )
+        .await;
+        assert_eq!(copied.as_deref(), Some("selected"));
+
+        let copied = wait_for_copy(|| Some(String::new()), Duration::from_millis(50)).await;
+        assert_eq!(copied, None);
+    }
+}
@@ -0,0 +1,303 @@
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::error::MiloError;
+use crate::providers::ANTHROPIC_VERSION;
+use crate::settings::ProviderKind;
+
+/// What a provider reports about an API key. Sent to the frontend by
+/// `validate_api_key` and the `key-status-changed` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyStatus {
+    /// Whether transforms can use the key
+    pub valid: bool,
+    /// Why the key can't be used
+    pub error: Option<String>,
+    /// Models the key has access to
+    pub models: Vec<String>,
+    /// Dollars spent so far, when the proxy tracks spend
+    pub spend: Option<f64>,
+    pub max_budget: Option<f64>,
+    /// Dollars left before the key hits its budget; None when it has no budget
+    pub remaining_budget: Option<f64>,
+    pub checked_at: DateTime<Utc>,
+}
+
+impl KeyStatus {
+    pub fn valid(models: Vec<String>) -> Self {
+        Self {
+            valid: true,
+            error: None,
+            models,
+            spend: None,
+            max_budget: None,
+            remaining_budget: None,
+            checked_at: Utc::now(),
+        }
+    }
+
+    pub fn invalid(error: impl Into<String>) -> Self {
+        Self {
+            valid: false,
+            error: Some(error.into()),
+            models: Vec::new(),
+            spend: None,
+            max_budget: None,
+            remaining_budget: None,
+            checked_at: Utc::now(),
+        }
+    }
+
+    /// Message for the tray when the key is unusable or its budget is below
+    /// `low_budget_threshold` dollars
+    pub fn warning(&self, low_budget_threshold: f64) -> Option<String> {
+        if !self.valid {
+            return Some(
+                self.error
+                    .clone()
+                    .unwrap_or_else(|| "API key is invalid".to_string()),
+            );
+        }
+        match self.remaining_budget {
+            Some(remaining) if remaining < low_budget_threshold => {
+                Some(format!("Only ${:.2} of API budget left", remaining))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    data: Vec<Model>,
+}
+
+#[derive(Deserialize)]
+struct Model {
+    id: String,
+}
+
+// Response of Ollama's `GET /api/tags`
+#[derive(Deserialize)]
+struct OllamaTags {
+    models: Vec<OllamaModel>,
+}
+
+#[derive(Deserialize)]
+struct OllamaModel {
+    name: String,
+}
+
+// Response of LiteLLM's `GET /key/info`
+#[derive(Deserialize)]
+struct KeyInfoResponse {
+    info: KeyInfo,
+}
+
+#[derive(Deserialize, Default)]
+struct KeyInfo {
+    #[serde(default)]
+    spend: Option<f64>,
+    #[serde(default)]
+    max_budget: Option<f64>,
+}
+
+/// Checks `api_key` against a server of the given kind by listing its models
+pub async fn check_provider(
+    http: &reqwest::Client,
+    kind: ProviderKind,
+    base_url: &str,
+    api_key: &str,
+) -> Result<KeyStatus, MiloError> {
+    let base_url = base_url.trim_end_matches('/');
+    let models = match kind {
+        ProviderKind::Litellm | ProviderKind::OpenaiCompatible => {
+            return check_key(http, base_url, api_key).await
+        }
+        ProviderKind::Anthropic => {
+            let request = http
+                .get(format!("{}/v1/models", base_url))
+                .header("x-api-key", api_key)
+                .header("anthropic-version", ANTHROPIC_VERSION);
+            list_models::<ModelList>(request)
+                .await
+                .map(|list| list.data.into_iter().map(|model| model.id).collect())
+        }
+        ProviderKind::Ollama => {
+            list_models::<OllamaTags>(http.get(format!("{}/api/tags", base_url)))
+                .await
+                .map(|tags| tags.models.into_iter().map(|model| model.name).collect())
+        }
+    };
+    models.map(KeyStatus::valid).or_else(rejected)
+}
+
+/// Asks the proxy at `base_url` whether `api_key` works. A rejected key is an
+/// `Ok` status; errors mean the check itself failed, e.g. the proxy is down.
+pub async fn check_key(
+    http: &reqwest::Client,
+    base_url: &str,
+    api_key: &str,
+) -> Result<KeyStatus, MiloError> {
+    let base_url = base_url.trim_end_matches('/');
+    let request = http
+        .get(format!("{}/models", base_url))
+        .bearer_auth(api_key);
+    let models = match list_models::<ModelList>(request).await {
+        Ok(list) => list.data.into_iter().map(|model| model.id).collect(),
+        Err(error) => return rejected(error),
+    };
+
+    // Budgets are a LiteLLM feature; other OpenAI-compatible servers lack the endpoint
+    let info = match http
+        .get(format!("{}/key/info", base_url))
+        .bearer_auth(api_key)
+        .send()
+        .await
+    {
+        Ok(response) if response.status().is_success() => response
+            .json::<KeyInfoResponse>()
+            .await
+            .map(|response| response.info)
+            .unwrap_or_default(),
+        _ => KeyInfo::default(),
+    };
+
+    Ok(KeyStatus {
+        spend: info.spend,
+        max_budget: info.max_budget,
+        remaining_budget: info
+            .max_budget
+            .map(|max_budget| (max_budget - info.spend.unwrap_or(0.0)).max(0.0)),
+        ..KeyStatus::valid(models)
+    })
+}
+
+/// Sends a GET to a model-list route and decodes the response
+pub async fn list_models<T: DeserializeOwned>(
+    request: reqwest::RequestBuilder,
+) -> Result<T, MiloError> {
+    let response = request.send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(MiloError::from_status(status.as_u16(), &body));
+    }
+    Ok(response.json().await?)
+}
+
+// A key the server refuses is a result of the check; any other error means
+// the check itself failed
+fn rejected(error: MiloError) -> Result<KeyStatus, MiloError> {
+    match error {
+        MiloError::Unauthorized | MiloError::Forbidden | MiloError::QuotaExceeded => {
+            Ok(KeyStatus::invalid(error.to_string()))
+        }
+        error => Err(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub_server::{StubResponse, StubServer};
+
+    const MODELS: &str = r#"{"object":"list","data":[{"id":"gpt-4o-mini","object":"model"},{"id":"claude-3-5-haiku","object":"model"}]}"#;
+
+    #[tokio::test]
+    async fn test_valid_key_reports_models_and_budget() {
+        let server = StubServer::start(vec![
+            StubResponse::raw(200, MODELS),
+            StubResponse::raw(
+                200,
+                r#"{"key":"sk-test","info":{"spend":9.5,"max_budget":10.0,"models":[]}}"#,
+            ),
+        ])
+        .await;
+
+        let status = check_key(&reqwest::Client::new(), server.base_url(), "sk-test")
+            .await
+            .unwrap();
+        assert!(status.valid);
+        assert_eq!(status.models, vec!["gpt-4o-mini", "claude-3-5-haiku"]);
+        assert_eq!(status.remaining_budget, Some(0.5));
+        assert_eq!(
+            status.warning(1.0).as_deref(),
+            Some("Only $0.50 of API budget left")
+        );
+        assert_eq!(status.warning(0.25), None);
+        assert_eq!(server.paths(), vec!["/v1/models", "/v1/key/info"]);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_key_is_invalid() {
+        let server = StubServer::start(vec![StubResponse::error(
+            401,
+            "invalid_api_key",
+            "auth_error",
+        )])
+        .await;
+
+        let status = check_key(&reqwest::Client::new(), server.base_url(), "sk-bad")
+            .await
+            .unwrap();
+        assert!(!status.valid);
+        assert!(status.warning(0.0).is_some());
+        // Nothing else is asked once the key is rejected
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_server_without_key_info_has_no_budget() {
+        let server = StubServer::start(vec![
+            StubResponse::raw(200, MODELS),
+            StubResponse::raw(404, "not found"),
+        ])
+        .await;
+
+        let status = check_key(&reqwest::Client::new(), server.base_url(), "sk-test")
+            .await
+            .unwrap();
+        assert!(status.valid);
+        assert_eq!(status.remaining_budget, None);
+        assert_eq!(status.warning(1.0), None);
+
+        let server = StubServer::start(vec![StubResponse::raw(500, "down")]).await;
+        let result = check_key(&reqwest::Client::new(), server.base_url(), "sk-test").await;
+        assert!(matches!(result, Err(MiloError::Server { status: 500, .. })));
+    }
+
+    #[tokio::test]
+    async fn test_checks_ollama_and_anthropic() {
+        let server = StubServer::start(vec![StubResponse::raw(
+            200,
+            r#"{"models":[{"name":"llama3.2:latest","size":2019393189}]}"#,
+        )])
+        .await;
+        let base_url = server.base_url().trim_end_matches("/v1");
+        let status = check_provider(&reqwest::Client::new(), ProviderKind::Ollama, base_url, "")
+            .await
+            .unwrap();
+        assert!(status.valid);
+        assert_eq!(status.models, vec!["llama3.2:latest"]);
+        assert_eq!(server.paths(), vec!["/api/tags"]);
+
+        let server = StubServer::start(vec![StubResponse::error(
+            401,
+            "authentication_error",
+            "authentication_error",
+        )])
+        .await;
+        let base_url = server.base_url().trim_end_matches("/v1");
+        let status = check_provider(
+            &reqwest::Client::new(),
+            ProviderKind::Anthropic,
+            base_url,
+            "sk-ant-bad",
+        )
+        .await
+        .unwrap();
+        assert!(!status.valid);
+        assert_eq!(server.paths(), vec!["/v1/models"]);
+    }
+}
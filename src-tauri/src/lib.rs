@@ -1,13 +1,30 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 mod api;
+mod chunking;
+mod cache;
+mod clipboard;
 mod config;
 mod core;
+mod credentials;
+mod error;
+mod formatting;
 mod history;
+mod input;
+mod key_status;
+mod models;
 mod notifications;
+mod providers;
+mod retry;
+mod review;
+mod rich_text;
+mod secrets;
 mod settings;
 mod shortcuts;
 mod state;
+#[cfg(test)]
+mod stub_server;
 mod system;
+mod template;
 mod transform;
 mod tray;
 
@@ -18,6 +35,7 @@ use tauri::Manager;
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let settings = Settings::load();
+    let template_problems = settings.template_problems();
     let app_state = AppState::new(settings);
 
     tauri::Builder::default()
@@ -32,6 +50,17 @@ pub fn run() {
                 println!("   Event state: {:?}", event.state());
 
                 match event.state() {
+                    tauri_plugin_global_shortcut::ShortcutState::Pressed if shortcuts::is_undo_shortcut(shortcut) => {
+                        println!("⬇️  Undo shortcut PRESSED - restoring original text");
+                        let app_handle = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            // Turning shortcuts off in Settings covers undo as well as transform
+                            let state = app_handle.state::<AppState>();
+                            if state.settings.lock().await.is_shortcut_enabled() {
+                                core::undo_with_notification(&app_handle);
+                            }
+                        });
+                    }
                     tauri_plugin_global_shortcut::ShortcutState::Pressed => {
                         println!("⬇️  Shortcut PRESSED - triggering transform");
                         let app_handle = app.clone();
@@ -39,16 +68,13 @@ pub fn run() {
                             if let Err(e) = core::transform_clip_with_setting(app_handle.clone(), true).await {
                                 println!("❌ Transform error: {}", e);
 
-                                if e.contains("rate limit") || e.contains("Rate limit") {
-                                    let notification_handle = app_handle.clone();
-                                    tokio::spawn(async move {
-                                        crate::notifications::show_notification(
-                                            &notification_handle,
-                                            "Milo - Rate Limited",
-                                            "Not enough API balance! Please top up your account and try again.",
-                                        );
-                                    });
-                                }
+                                let notification_handle = app_handle.clone();
+                                tokio::spawn(async move {
+                                    crate::notifications::show_error_notification(
+                                        &notification_handle,
+                                        &e,
+                                    );
+                                });
                             } else {
                                 println!("✅ Transform completed successfully");
                             }
@@ -60,10 +86,31 @@ pub fn run() {
                 }
             })
             .build())
-        .setup(|app| {
+        .setup(move |app| {
             println!("Starting Milo app...");
+            secrets::migrate_plaintext_keys();
+            // Prompts naming a deleted variable would only fail once they are used
+            if !template_problems.is_empty() {
+                notifications::show_notification(
+                    app.handle(),
+                    "Milo - Check Your Prompts",
+                    template_problems.join("\n"),
+                );
+            }
             let _tray = tray::create_tray_menu(app)?;
 
+            // Flag an invalid or drained key before the first transform fails
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = api::refresh_key_status(&handle, false).await {
+                    println!("⚠️ Failed to check API key: {}", e);
+                }
+                // Context windows from the catalog size the chunks of long texts
+                if let Err(e) = api::model_catalog(&handle, false).await {
+                    println!("⚠️ Failed to list models: {}", e);
+                }
+            });
+
             // Register global shortcuts
             #[cfg(desktop)]
             shortcuts::register_shortcuts(&app.handle())?;
@@ -80,15 +127,30 @@ pub fn run() {
             api::save_litellm_api_key,
             api::get_litellm_api_key,
             api::get_usage_key_preview,
+            api::validate_api_key,
+            api::get_app_config,
+            api::list_available_models,
+            credentials::list_credentials,
+            credentials::add_credential,
+            credentials::rename_credential,
+            credentials::delete_credential,
+            credentials::test_credential,
             api::save_settings,
             api::get_settings,
             api::show_settings,
             api::relaunch_app,
             core::transform_clipboard,
             core::transform_clip_with_setting,
+            core::cancel_transformation,
+            core::is_transforming,
+            review::get_pending_review,
+            review::resolve_review,
+            core::undo_last_transformation,
             shortcuts::get_current_shortcut,
             shortcuts::update_shortcut,
             shortcuts::unregister_shortcut,
+            shortcuts::get_undo_shortcut,
+            shortcuts::update_undo_shortcut,
             history::add_transformation_to_history,
             history::get_transformation_history,
             history::clear_transformation_history,
@@ -97,12 +159,15 @@ pub fn run() {
             history::get_daily_stats,
         ])
         .on_window_event(|_app, event| match event {
-            tauri::WindowEvent::CloseRequested { api, .. } => {
+            tauri::WindowEvent::Destroyed if _app.label() == review::REVIEW_WINDOW => {
+                review::discard_pending_review(_app.app_handle());
+            }
+            tauri::WindowEvent::CloseRequested { api, .. } if _app.label() == "main" => {
                 let window = _app.get_webview_window("main").unwrap();
                 window.hide().unwrap();
                 api.prevent_close();
             }
-            tauri::WindowEvent::Focused(is_focused) => {
+            tauri::WindowEvent::Focused(is_focused) if _app.label() == "main" => {
                 if *is_focused {
                     println!("Window focused - ensuring proper z-order and app activation");
                     #[cfg(target_os = "macos")]
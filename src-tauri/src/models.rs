@@ -0,0 +1,161 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::MiloError;
+use crate::key_status::list_models;
+
+/// How long a fetched model list is reused
+pub const MODEL_CATALOG_TTL: Duration = Duration::from_secs(60 * 60);
+/// How long a failed lookup is reported again instead of retried, so saving
+/// settings offline doesn't wait for a timeout every time
+pub const MODEL_CATALOG_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// A model the endpoint offers
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ModelInfo {
+    pub id: String,
+    /// Input tokens the model accepts, when the endpoint reports it
+    pub context_window: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+// OpenAI only sends the id; other servers add the context size under one of these names
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+    #[serde(default, alias = "context_length", alias = "max_input_tokens")]
+    context_window: Option<u32>,
+}
+
+// Response of LiteLLM's `GET /model/info`
+#[derive(Deserialize)]
+struct ModelInfoList {
+    data: Vec<ModelInfoEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelInfoEntry {
+    model_name: String,
+    #[serde(default)]
+    model_info: Option<ModelLimits>,
+}
+
+// `max_tokens` is left out: for many models it is the output limit, not the context window
+#[derive(Deserialize)]
+struct ModelLimits {
+    #[serde(default)]
+    max_input_tokens: Option<u32>,
+}
+
+/// Lists the models of the OpenAI-compatible endpoint at `base_url`, sorted by id
+pub async fn fetch_catalog(
+    http: &reqwest::Client,
+    base_url: &str,
+    api_key: &str,
+) -> Result<Vec<ModelInfo>, MiloError> {
+    let base_url = base_url.trim_end_matches('/');
+    let request = http
+        .get(format!("{}/models", base_url))
+        .bearer_auth(api_key);
+    let mut catalog: Vec<ModelInfo> = list_models::<ModelList>(request)
+        .await?
+        .data
+        .into_iter()
+        .map(|entry| ModelInfo {
+            id: entry.id,
+            context_window: entry.context_window,
+        })
+        .collect();
+
+    // LiteLLM leaves context windows out of `/models` but has them on its own route
+    if catalog.iter().any(|model| model.context_window.is_none()) {
+        let request = http
+            .get(format!("{}/model/info", base_url))
+            .bearer_auth(api_key);
+        if let Ok(info) = list_models::<ModelInfoList>(request).await {
+            for model in catalog
+                .iter_mut()
+                .filter(|model| model.context_window.is_none())
+            {
+                model.context_window = info
+                    .data
+                    .iter()
+                    .find(|entry| entry.model_name == model.id)
+                    .and_then(|entry| entry.model_info.as_ref())
+                    .and_then(|limits| limits.max_input_tokens);
+            }
+        }
+    }
+
+    catalog.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(catalog)
+}
+
+/// Context window of `model`, if the catalog knows it
+pub fn context_window(catalog: &[ModelInfo], model: &str) -> Option<u32> {
+    catalog
+        .iter()
+        .find(|info| info.id == model)
+        .and_then(|info| info.context_window)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub_server::{StubResponse, StubServer};
+
+    #[tokio::test]
+    async fn test_catalog_fills_in_litellm_context_windows() {
+        let server = StubServer::start(vec![
+            StubResponse::raw(
+                200,
+                r#"{"object":"list","data":[{"id":"gpt-4o-mini","object":"model"},{"id":"claude-3-5-haiku","object":"model"}]}"#,
+            ),
+            StubResponse::raw(
+                200,
+                r#"{"data":[{"model_name":"gpt-4o-mini","model_info":{"max_input_tokens":128000,"max_tokens":16384}},{"model_name":"claude-3-5-haiku","model_info":{"max_tokens":8192}}]}"#,
+            ),
+        ])
+        .await;
+
+        let catalog = fetch_catalog(&reqwest::Client::new(), server.base_url(), "sk-test")
+            .await
+            .unwrap();
+        assert_eq!(
+            catalog,
+            vec![
+                ModelInfo {
+                    id: "claude-3-5-haiku".to_string(),
+                    context_window: None,
+                },
+                ModelInfo {
+                    id: "gpt-4o-mini".to_string(),
+                    context_window: Some(128_000),
+                },
+            ]
+        );
+        assert_eq!(server.paths(), vec!["/v1/models", "/v1/model/info"]);
+        assert_eq!(context_window(&catalog, "gpt-4o-mini"), Some(128_000));
+        assert_eq!(context_window(&catalog, "unknown"), None);
+    }
+
+    #[tokio::test]
+    async fn test_catalog_uses_context_length_from_models() {
+        let server = StubServer::start(vec![StubResponse::raw(
+            200,
+            r#"{"data":[{"id":"llama-3.1-8b","context_length":131072}]}"#,
+        )])
+        .await;
+
+        let catalog = fetch_catalog(&reqwest::Client::new(), server.base_url(), "sk-test")
+            .await
+            .unwrap();
+        assert_eq!(catalog[0].context_window, Some(131_072));
+        assert_eq!(server.requests().len(), 1);
+    }
+}
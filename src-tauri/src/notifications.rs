@@ -3,13 +3,19 @@ use std::{
     process::Command,
 };
 
-use tauri::AppHandle;
+use tauri::{AppHandle, Runtime};
 use tauri_plugin_notification::NotificationExt;
 
+use crate::error::MiloError;
+
 /// Attempts to show a system notification while shielding the dev runtime from
 /// macOS-specific panics. In development on macOS we fall back to `osascript`
 /// so the Tokio worker doesn't abort when the native bridge returns null.
-pub fn show_notification(handle: &AppHandle, title: impl Into<String>, body: impl Into<String>) {
+pub fn show_notification<R: Runtime>(
+    handle: &AppHandle<R>,
+    title: impl Into<String>,
+    body: impl Into<String>,
+) {
     let title: String = title.into();
     let body: String = body.into();
 
@@ -51,6 +57,62 @@ pub fn show_notification(handle: &AppHandle, title: impl Into<String>, body: imp
     }
 }
 
+/// Notifies the user about a failed transform, if the error is one they can act on
+pub fn show_error_notification<R: Runtime>(handle: &AppHandle<R>, error: &MiloError) {
+    if let Some((title, body)) = error_notification(error) {
+        show_notification(handle, title, body);
+    }
+}
+
+/// Title and body of the notification for a failed transform. Errors the user
+/// can't act on, or caused themselves like a cancel, get none.
+pub fn error_notification(error: &MiloError) -> Option<(&'static str, &'static str)> {
+    let notification = match error {
+        MiloError::RateLimited { .. } | MiloError::QuotaExceeded => (
+            "Milo - Rate Limited",
+            "Not enough API balance! Please top up your account and try again.",
+        ),
+        MiloError::Unauthorized => (
+            "Milo - Invalid Key",
+            "Your usage key was rejected. Please check it in Settings.",
+        ),
+        MiloError::Forbidden => (
+            "Milo - Access Denied",
+            "API access forbidden. Please check your account status.",
+        ),
+        MiloError::Server { .. } => (
+            "Milo - Service Unavailable",
+            "The AI service is having problems. Please try again in a moment.",
+        ),
+        MiloError::Network(_) => (
+            "Milo - Connection Problem",
+            "Could not reach the AI service. Please check your connection and try again.",
+        ),
+        MiloError::Timeout => (
+            "Milo - Request Timed Out",
+            "The AI service took too long to respond. You can raise the timeout in Settings.",
+        ),
+        MiloError::InputTooLarge { .. } => (
+            "Milo - Text Too Long",
+            "The copied text is longer than the size limit in Settings.",
+        ),
+        MiloError::NoSelection => (
+            "Milo - Nothing Selected",
+            "Select some text first, or turn off replace selection in Settings.",
+        ),
+        MiloError::Input(_) => (
+            "Milo - Input Unavailable",
+            "Milo could not send copy and paste keystrokes. Check accessibility permissions.",
+        ),
+        MiloError::Busy => (
+            "Milo - Busy",
+            "A transformation is already running. Please wait for it to finish.",
+        ),
+        _ => return None,
+    };
+    Some(notification)
+}
+
 fn show_dev_notification(title: &str, body: &str) -> Result<(), String> {
     #[cfg(target_os = "macos")]
     {
@@ -84,3 +146,130 @@ fn show_dev_notification(title: &str, body: &str) -> Result<(), String> {
 fn escape_osascript(input: &str) -> String {
     input.replace('"', "\\\"")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub_server::{stream_with_stub, transform_with_stub, StubResponse};
+    use std::time::Duration;
+
+    const TIMEOUT: Duration = Duration::from_millis(200);
+
+    // Title of the notification shown when the provider answers with `response`
+    async fn notification_for(response: StubResponse) -> Option<&'static str> {
+        let (result, _server) = transform_with_stub(vec![response], 1, TIMEOUT).await;
+        title_for(&result.unwrap_err())
+    }
+
+    // Same, for the streaming path that is on by default
+    async fn streamed_notification_for(response: StubResponse) -> Option<&'static str> {
+        let (result, _, _server) = stream_with_stub(vec![response], 1, TIMEOUT).await;
+        title_for(&result.unwrap_err())
+    }
+
+    fn title_for(error: &MiloError) -> Option<&'static str> {
+        error_notification(error).map(|(title, _)| title)
+    }
+
+    #[tokio::test]
+    async fn test_provider_errors_notify() {
+        assert_eq!(
+            notification_for(StubResponse::error(429, "rate_limit_exceeded", "requests")).await,
+            Some("Milo - Rate Limited")
+        );
+        assert_eq!(
+            notification_for(StubResponse::error(
+                401,
+                "invalid_api_key",
+                "invalid_request_error"
+            ))
+            .await,
+            Some("Milo - Invalid Key")
+        );
+        assert_eq!(
+            notification_for(StubResponse::error(403, "403", "auth_error")).await,
+            Some("Milo - Access Denied")
+        );
+        assert_eq!(
+            notification_for(StubResponse::completion("the text").delayed(Duration::from_secs(1)))
+                .await,
+            Some("Milo - Request Timed Out")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_streamed_errors_notify() {
+        assert_eq!(
+            streamed_notification_for(StubResponse::error(
+                401,
+                "invalid_api_key",
+                "invalid_request_error"
+            ))
+            .await,
+            Some("Milo - Invalid Key")
+        );
+        assert_eq!(
+            streamed_notification_for(StubResponse::error(429, "rate_limit_exceeded", "requests"))
+                .await,
+            Some("Milo - Rate Limited")
+        );
+        assert_eq!(
+            streamed_notification_for(StubResponse::error(403, "403", "auth_error")).await,
+            Some("Milo - Access Denied")
+        );
+        assert_eq!(
+            streamed_notification_for(StubResponse::error(
+                429,
+                "insufficient_quota",
+                "insufficient_quota"
+            ))
+            .await,
+            Some("Milo - Rate Limited")
+        );
+        assert_eq!(
+            streamed_notification_for(
+                StubResponse::stream(&["the text"]).delayed(Duration::from_secs(1))
+            )
+            .await,
+            Some("Milo - Request Timed Out")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_server_errors_notify_once_retries_run_out() {
+        assert_eq!(
+            notification_for(StubResponse::raw(500, "Internal Server Error")).await,
+            Some("Milo - Service Unavailable")
+        );
+        assert_eq!(
+            streamed_notification_for(StubResponse::error(503, "", "")).await,
+            Some("Milo - Service Unavailable")
+        );
+
+        let (result, server) =
+            transform_with_stub(vec![StubResponse::raw(502, "Bad Gateway")], 3, TIMEOUT).await;
+        assert_eq!(server.requests().len(), 3);
+        assert_eq!(
+            title_for(&result.unwrap_err()),
+            Some("Milo - Service Unavailable")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unactionable_errors_stay_quiet() {
+        assert_eq!(notification_for(StubResponse::empty_choices()).await, None);
+        assert_eq!(
+            notification_for(StubResponse::raw(200, "not json")).await,
+            None
+        );
+    }
+
+    #[test]
+    fn test_cancel_stays_quiet() {
+        assert_eq!(error_notification(&MiloError::Cancelled), None);
+        assert_eq!(
+            error_notification(&MiloError::Busy).map(|(title, _)| title),
+            Some("Milo - Busy")
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::http::error_for_status;
+use super::{CompletionRequest, TransformProvider};
+use crate::error::MiloError;
+
+/// Sent as `anthropic-version` with every request
+pub const ANTHROPIC_VERSION: &str = "2023-06-01";
+/// The messages API requires `max_tokens`; used when the prompt doesn't set one
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+#[derive(Serialize)]
+struct MessagesRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<&'a str>,
+    messages: Vec<Message<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<&'a [String]>,
+}
+
+#[derive(Serialize)]
+struct Message<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Deserialize)]
+struct MessagesResponse {
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: Option<String>,
+}
+
+/// Anthropic-style messages API (`POST /v1/messages`)
+pub struct AnthropicProvider {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+}
+
+impl AnthropicProvider {
+    pub fn new(http: reqwest::Client, base_url: String, api_key: String, model: String) -> Self {
+        Self {
+            http,
+            base_url,
+            api_key,
+            model,
+        }
+    }
+}
+
+#[async_trait]
+impl TransformProvider for AnthropicProvider {
+    fn name(&self) -> &str {
+        "anthropic"
+    }
+
+    fn default_model(&self) -> &str {
+        &self.model
+    }
+
+    async fn complete(&self, request: CompletionRequest) -> Result<String, MiloError> {
+        let url = format!("{}/v1/messages", self.base_url.trim_end_matches('/'));
+        let body = MessagesRequest {
+            model: &request.model,
+            max_tokens: request.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            system: Some(request.system_prompt.as_str()).filter(|system| !system.is_empty()),
+            messages: vec![Message {
+                role: "user",
+                content: &request.text,
+            }],
+            temperature: request.temperature,
+            top_p: request.top_p,
+            stop_sequences: request.stop.as_deref(),
+        };
+
+        let response = self
+            .http
+            .post(url)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&body)
+            .send()
+            .await?;
+        let response: MessagesResponse = error_for_status(response).await?.json().await?;
+
+        let text: String = response
+            .content
+            .into_iter()
+            .filter(|block| block.kind == "text")
+            .filter_map(|block| block.text)
+            .collect();
+
+        if text.is_empty() {
+            Err(MiloError::EmptyCompletion)
+        } else {
+            Ok(text)
+        }
+    }
+}
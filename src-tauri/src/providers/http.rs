@@ -0,0 +1,44 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::error::{parse_retry_after, MiloError};
+use crate::settings::TimeoutSettings;
+
+// One pooled client for every provider, rebuilt only when the timeouts change
+static SHARED_CLIENT: Mutex<Option<(TimeoutSettings, reqwest::Client)>> = Mutex::new(None);
+
+/// HTTP client with the configured timeouts. It is shared so connections to
+/// the provider are kept alive and reused across transforms.
+pub fn shared_client(timeouts: &TimeoutSettings) -> Result<reqwest::Client, MiloError> {
+    let mut shared = SHARED_CLIENT.lock().unwrap();
+    if let Some((settings, client)) = shared.as_ref() {
+        if settings == timeouts {
+            return Ok(client.clone());
+        }
+    }
+
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_millis(timeouts.connect_timeout_ms))
+        .timeout(Duration::from_millis(timeouts.request_timeout_ms))
+        .build()
+        .map_err(|e| MiloError::Network(format!("Failed to build HTTP client: {}", e)))?;
+    *shared = Some((timeouts.clone(), client.clone()));
+    Ok(client)
+}
+
+/// Passes a successful response through and turns any other into the error its
+/// status stands for, keeping the server's `Retry-After` delay
+pub async fn error_for_status(response: reqwest::Response) -> Result<reqwest::Response, MiloError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_retry_after);
+    let text = response.text().await.unwrap_or_default();
+    Err(MiloError::from_status(status.as_u16(), &text).with_retry_after(retry_after))
+}
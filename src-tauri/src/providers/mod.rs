@@ -0,0 +1,224 @@
+mod anthropic;
+mod http;
+mod ollama;
+mod openai;
+
+use async_trait::async_trait;
+
+use crate::api::{get_api_key, get_litellm_api_key};
+use crate::config::CONFIG;
+use crate::error::MiloError;
+use crate::secrets::{self, SecretStore, SystemSecrets};
+use crate::settings::{Credential, ProviderKind, ProviderSettings, TimeoutSettings};
+
+pub use anthropic::{AnthropicProvider, ANTHROPIC_VERSION};
+pub use http::shared_client;
+pub use ollama::OllamaProvider;
+pub use openai::OpenAiCompatibleProvider;
+
+pub const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+pub const ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com";
+pub const ANTHROPIC_DEFAULT_MODEL: &str = "claude-3-5-haiku-latest";
+pub const OLLAMA_BASE_URL: &str = "http://localhost:11434";
+pub const OLLAMA_DEFAULT_MODEL: &str = "llama3.2";
+
+/// A single system prompt + user text exchange
+#[derive(Debug, Clone)]
+pub struct CompletionRequest {
+    pub model: String,
+    /// Omitted from the request when empty
+    pub system_prompt: String,
+    pub text: String,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub stop: Option<Vec<String>>,
+}
+
+/// Receives each chunk of a streamed completion
+pub type OnDelta<'a> = dyn Fn(&str) + Send + Sync + 'a;
+
+/// A chat backend that can rewrite text
+#[async_trait]
+pub trait TransformProvider: Send + Sync {
+    /// Short identifier used in logs
+    fn name(&self) -> &str;
+
+    /// Model used when the prompt doesn't specify one
+    fn default_model(&self) -> &str;
+
+    /// Sends the request and returns the completion text
+    async fn complete(&self, request: CompletionRequest) -> Result<String, MiloError>;
+
+    /// Like `complete`, but calls `on_delta` with each chunk as it arrives.
+    /// Providers without streaming support deliver the whole text as one chunk.
+    async fn complete_stream(
+        &self,
+        request: CompletionRequest,
+        on_delta: &OnDelta<'_>,
+    ) -> Result<String, MiloError> {
+        let text = self.complete(request).await?;
+        on_delta(&text);
+        Ok(text)
+    }
+
+    /// `n` alternative completions for the same request. The default sends
+    /// `n` requests at increasing temperatures.
+    async fn complete_candidates(
+        &self,
+        request: CompletionRequest,
+        n: u32,
+    ) -> Result<Vec<String>, MiloError> {
+        let requests = (0..n).map(|index| {
+            let mut request = request.clone();
+            request.temperature = Some(candidate_temperature(request.temperature, index));
+            self.complete(request)
+        });
+        futures::future::try_join_all(requests).await
+    }
+}
+
+/// Temperature for the `index`th candidate: the first keeps the prompt's own
+/// setting and each further one samples a little more freely
+pub fn candidate_temperature(base: Option<f32>, index: u32) -> f32 {
+    let base = base.unwrap_or(0.7);
+    if index == 0 {
+        return base;
+    }
+    (base + 0.2 * index as f32).min(1.0).max(base)
+}
+
+/// Endpoint used when neither the prompt nor its credential sets one
+pub fn default_base_url(kind: ProviderKind) -> String {
+    match kind {
+        ProviderKind::Litellm => CONFIG.litellm_base_url.clone(),
+        ProviderKind::OpenaiCompatible => OPENAI_BASE_URL.to_string(),
+        ProviderKind::Anthropic => ANTHROPIC_BASE_URL.to_string(),
+        ProviderKind::Ollama => OLLAMA_BASE_URL.to_string(),
+    }
+}
+
+/// Key saved for `credential` in `store`. One whose provider needs a key fails
+/// without it, rather than sending a request that can only be rejected.
+pub fn credential_key(
+    store: &dyn SecretStore,
+    credential: &Credential,
+) -> Result<String, MiloError> {
+    let key = store
+        .load(&secrets::credential_secret_name(&credential.id))
+        .map_err(|e| MiloError::Settings(format!("Failed to get key of credential: {}", e)))?
+        .filter(|key| !key.is_empty());
+    match key {
+        Some(key) => Ok(key),
+        None if credential.kind.needs_api_key() => Err(MiloError::Settings(format!(
+            "Credential '{}' has no saved key",
+            credential.name
+        ))),
+        None => Ok(String::new()),
+    }
+}
+
+// Key of the prompt's credential, or the default key for its provider
+async fn resolve_api_key(
+    settings: &ProviderSettings,
+    credential: Option<&Credential>,
+) -> Result<String, MiloError> {
+    if let Some(credential) = credential {
+        return credential_key(&SystemSecrets, credential);
+    }
+    match settings.kind {
+        ProviderKind::Litellm => get_litellm_api_key()
+            .await
+            .map_err(|e| MiloError::Settings(format!("Failed to get LiteLLM API key: {}", e))),
+        _ => get_api_key()
+            .await
+            .map_err(|e| MiloError::Settings(format!("Failed to get API key: {}", e))),
+    }
+}
+
+/// Builds the provider described by `settings`, resolving its API key from
+/// `credential` when the prompt selects one
+pub async fn build_provider(
+    settings: &ProviderSettings,
+    credential: Option<&Credential>,
+    timeouts: &TimeoutSettings,
+) -> Result<Box<dyn TransformProvider>, MiloError> {
+    let base_url = settings
+        .base_url
+        .clone()
+        .unwrap_or_else(|| default_base_url(settings.kind));
+    let model = settings.model.clone();
+    let http = http::shared_client(timeouts)?;
+
+    let provider: Box<dyn TransformProvider> = match settings.kind {
+        ProviderKind::Litellm => Box::new(OpenAiCompatibleProvider::new(
+            http,
+            "litellm",
+            base_url,
+            resolve_api_key(settings, credential).await?,
+            model.unwrap_or_else(|| CONFIG.default_model.clone()),
+        )),
+        ProviderKind::OpenaiCompatible => Box::new(OpenAiCompatibleProvider::new(
+            http,
+            "openai",
+            base_url,
+            resolve_api_key(settings, credential).await?,
+            model.unwrap_or_else(|| CONFIG.default_model.clone()),
+        )),
+        ProviderKind::Anthropic => Box::new(AnthropicProvider::new(
+            http,
+            base_url,
+            resolve_api_key(settings, credential).await?,
+            model.unwrap_or_else(|| ANTHROPIC_DEFAULT_MODEL.to_string()),
+        )),
+        ProviderKind::Ollama => Box::new(OllamaProvider::new(
+            http,
+            base_url,
+            model.unwrap_or_else(|| OLLAMA_DEFAULT_MODEL.to_string()),
+        )),
+    };
+
+    println!("Using {} provider", provider.name());
+    Ok(provider)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secrets::MemorySecrets;
+
+    #[test]
+    fn test_candidate_temperature() {
+        assert_eq!(candidate_temperature(Some(0.3), 0), 0.3);
+        assert!((candidate_temperature(Some(0.3), 1) - 0.5).abs() < 1e-6);
+        assert_eq!(candidate_temperature(None, 3), 1.0);
+        // Never lowers a temperature that is already above the cap
+        assert_eq!(candidate_temperature(Some(1.4), 2), 1.4);
+    }
+
+    #[test]
+    fn test_credential_without_saved_key() {
+        let store = MemorySecrets::with_secret(&secrets::credential_secret_name("work"), "sk-work");
+        let mut credential = Credential {
+            id: "work".to_string(),
+            name: "Work proxy".to_string(),
+            kind: ProviderKind::Litellm,
+            base_url: None,
+        };
+        assert_eq!(
+            credential_key(&store, &credential),
+            Ok("sk-work".to_string())
+        );
+
+        credential.id = "personal".to_string();
+        assert_eq!(
+            credential_key(&store, &credential),
+            Err(MiloError::Settings(
+                "Credential 'Work proxy' has no saved key".to_string()
+            ))
+        );
+
+        credential.kind = ProviderKind::Ollama;
+        assert_eq!(credential_key(&store, &credential), Ok(String::new()));
+    }
+}
@@ -0,0 +1,106 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::http::error_for_status;
+use super::{CompletionRequest, TransformProvider};
+use crate::error::MiloError;
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    stream: bool,
+    messages: Vec<Message<'a>>,
+    options: Options<'a>,
+}
+
+#[derive(Serialize)]
+struct Options<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<&'a [String]>,
+}
+
+#[derive(Serialize)]
+struct Message<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    message: Option<ResponseMessage>,
+}
+
+#[derive(Deserialize)]
+struct ResponseMessage {
+    content: String,
+}
+
+/// Ollama-compatible local server (`POST /api/chat`)
+pub struct OllamaProvider {
+    http: reqwest::Client,
+    base_url: String,
+    model: String,
+}
+
+impl OllamaProvider {
+    pub fn new(http: reqwest::Client, base_url: String, model: String) -> Self {
+        Self {
+            http,
+            base_url,
+            model,
+        }
+    }
+}
+
+#[async_trait]
+impl TransformProvider for OllamaProvider {
+    fn name(&self) -> &str {
+        "ollama"
+    }
+
+    fn default_model(&self) -> &str {
+        &self.model
+    }
+
+    async fn complete(&self, request: CompletionRequest) -> Result<String, MiloError> {
+        let url = format!("{}/api/chat", self.base_url.trim_end_matches('/'));
+        let body = ChatRequest {
+            model: &request.model,
+            stream: false,
+            messages: [
+                Message {
+                    role: "system",
+                    content: &request.system_prompt,
+                },
+                Message {
+                    role: "user",
+                    content: &request.text,
+                },
+            ]
+            .into_iter()
+            .filter(|message| !message.content.is_empty())
+            .collect(),
+            options: Options {
+                temperature: request.temperature,
+                top_p: request.top_p,
+                num_predict: request.max_tokens,
+                stop: request.stop.as_deref(),
+            },
+        };
+
+        let response = self.http.post(url).json(&body).send().await?;
+        let response: ChatResponse = error_for_status(response).await?.json().await?;
+
+        response
+            .message
+            .map(|message| message.content)
+            .filter(|content| !content.is_empty())
+            .ok_or(MiloError::EmptyCompletion)
+    }
+}
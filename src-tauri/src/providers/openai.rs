@@ -0,0 +1,208 @@
+use async_openai::types::{
+    ChatCompletionRequestMessage, ChatCompletionRequestSystemMessageArgs,
+    ChatCompletionRequestUserMessageArgs, CreateChatCompletionRequest,
+    CreateChatCompletionRequestArgs, CreateChatCompletionResponse,
+    CreateChatCompletionStreamResponse, Stop,
+};
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use super::http::error_for_status;
+use super::{CompletionRequest, OnDelta, TransformProvider};
+use crate::error::MiloError;
+
+// Error object some servers send inside an already started stream
+#[derive(Deserialize)]
+struct StreamError {
+    error: StreamErrorBody,
+}
+
+#[derive(Deserialize)]
+struct StreamErrorBody {
+    message: String,
+}
+
+/// OpenAI chat completions API, used for both the LiteLLM proxy and direct endpoints.
+/// Only async-openai's types are used: its client reports stream failures without
+/// their HTTP status and retries 429 and 5xx on its own, beneath `RetryPolicy`. So
+/// requests go through the shared reqwest client instead, which carries the timeouts
+/// and classifies failures by status whether or not the response streams.
+pub struct OpenAiCompatibleProvider {
+    name: String,
+    http: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+}
+
+impl OpenAiCompatibleProvider {
+    pub fn new(
+        http: reqwest::Client,
+        name: &str,
+        base_url: String,
+        api_key: String,
+        model: String,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            http,
+            base_url,
+            api_key,
+            model,
+        }
+    }
+
+    async fn send(
+        &self,
+        request: &CreateChatCompletionRequest,
+    ) -> Result<reqwest::Response, MiloError> {
+        let url = format!("{}/chat/completions", self.base_url.trim_end_matches('/'));
+        let response = self
+            .http
+            .post(url)
+            .bearer_auth(&self.api_key)
+            .json(request)
+            .send()
+            .await?;
+        error_for_status(response).await
+    }
+
+    fn build_request(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CreateChatCompletionRequest, MiloError> {
+        let mut messages: Vec<ChatCompletionRequestMessage> = Vec::with_capacity(2);
+        if !request.system_prompt.is_empty() {
+            let system_message = ChatCompletionRequestSystemMessageArgs::default()
+                .content(request.system_prompt)
+                .build()
+                .map_err(|e| MiloError::Api(format!("Failed to build system message: {}", e)))?;
+            messages.push(system_message.into());
+        }
+        let user_message = ChatCompletionRequestUserMessageArgs::default()
+            .content(request.text)
+            .build()
+            .map_err(|e| MiloError::Api(format!("Failed to build user message: {}", e)))?;
+        messages.push(user_message.into());
+
+        let mut args = CreateChatCompletionRequestArgs::default();
+        args.model(&request.model).messages(messages);
+        if let Some(temperature) = request.temperature {
+            args.temperature(temperature);
+        }
+        if let Some(top_p) = request.top_p {
+            args.top_p(top_p);
+        }
+        if let Some(max_tokens) = request.max_tokens {
+            args.max_completion_tokens(max_tokens);
+        }
+        if let Some(stop) = request.stop.filter(|stop| !stop.is_empty()) {
+            args.stop(Stop::StringArray(stop));
+        }
+
+        args.build()
+            .map_err(|e| MiloError::Api(format!("Failed to build chat completion request: {}", e)))
+    }
+}
+
+#[async_trait]
+impl TransformProvider for OpenAiCompatibleProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn default_model(&self) -> &str {
+        &self.model
+    }
+
+    async fn complete(&self, request: CompletionRequest) -> Result<String, MiloError> {
+        let request = self.build_request(request)?;
+
+        let response: CreateChatCompletionResponse = self.send(&request).await?.json().await?;
+
+        response
+            .choices
+            .first()
+            .and_then(|choice| choice.message.content.clone())
+            .ok_or(MiloError::EmptyCompletion)
+    }
+
+    async fn complete_candidates(
+        &self,
+        request: CompletionRequest,
+        n: u32,
+    ) -> Result<Vec<String>, MiloError> {
+        let mut request = self.build_request(request)?;
+        request.n = Some(n.min(u8::MAX as u32) as u8);
+
+        let response: CreateChatCompletionResponse = self.send(&request).await?.json().await?;
+
+        let candidates: Vec<String> = response
+            .choices
+            .into_iter()
+            .filter_map(|choice| choice.message.content)
+            .filter(|content| !content.trim().is_empty())
+            .collect();
+        if candidates.is_empty() {
+            return Err(MiloError::EmptyCompletion);
+        }
+        Ok(candidates)
+    }
+
+    async fn complete_stream(
+        &self,
+        request: CompletionRequest,
+        on_delta: &OnDelta<'_>,
+    ) -> Result<String, MiloError> {
+        let mut request = self.build_request(request)?;
+        request.stream = Some(true);
+        let mut response = self.send(&request).await?;
+
+        // Server-sent events: one `data: <json>` line per chunk, ended by `data: [DONE]`
+        let mut text = String::new();
+        let mut buffer: Vec<u8> = Vec::new();
+        'events: while let Some(bytes) = response.chunk().await? {
+            buffer.extend_from_slice(&bytes);
+            while let Some(end) = buffer.iter().position(|&byte| byte == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                let Some(data) = line.trim_end().strip_prefix("data:") else {
+                    continue;
+                };
+                let data = data.trim_start();
+                if data == "[DONE]" {
+                    break 'events;
+                }
+                if let Some(delta) = parse_delta(data)? {
+                    text.push_str(&delta);
+                    on_delta(&delta);
+                }
+            }
+        }
+
+        if text.is_empty() {
+            Err(MiloError::EmptyCompletion)
+        } else {
+            Ok(text)
+        }
+    }
+}
+
+// Text added by one stream chunk
+fn parse_delta(data: &str) -> Result<Option<String>, MiloError> {
+    match serde_json::from_str::<CreateChatCompletionStreamResponse>(data) {
+        Ok(chunk) => Ok(chunk
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.delta.content)
+            .filter(|content| !content.is_empty())),
+        Err(e) => match serde_json::from_str::<StreamError>(data) {
+            Ok(error) => Err(MiloError::Api(error.error.message)),
+            Err(_) => Err(MiloError::Api(format!(
+                "Failed to parse stream chunk: {}",
+                e
+            ))),
+        },
+    }
+}
@@ -0,0 +1,122 @@
+use std::{future::Future, time::Duration};
+
+use rand::Rng;
+
+use crate::error::MiloError;
+use crate::settings::RetryPolicy;
+
+/// Runs `operation` until it succeeds, fails with a non-retryable error or the
+/// policy runs out of attempts. Returns the result with the number of attempts made.
+pub async fn with_retry<T, F, Fut>(
+    policy: &RetryPolicy,
+    mut operation: F,
+) -> (Result<T, MiloError>, u32)
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<T, MiloError>>,
+{
+    let max_attempts = policy.max_attempts.max(1);
+    let mut attempt = 1;
+
+    loop {
+        match operation(attempt).await {
+            Ok(value) => return (Ok(value), attempt),
+            Err(error) if error.is_retryable() && attempt < max_attempts => {
+                let delay = delay_for(policy, attempt, &error);
+                println!(
+                    "Attempt {}/{} failed ({}), retrying in {:?}",
+                    attempt, max_attempts, error, delay
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(error) => return (Err(error), attempt),
+        }
+    }
+}
+
+/// Delay before the attempt following `attempt`. A server-provided
+/// `Retry-After` wins over the computed backoff.
+fn delay_for(policy: &RetryPolicy, attempt: u32, error: &MiloError) -> Duration {
+    if let Some(retry_after) = error.retry_after() {
+        return retry_after.min(Duration::from_millis(policy.max_delay_ms));
+    }
+
+    let exponent = attempt.saturating_sub(1).min(16);
+    let backoff = policy
+        .base_delay_ms
+        .saturating_mul(1 << exponent)
+        .min(policy.max_delay_ms);
+
+    let millis = if policy.jitter && backoff > 0 {
+        rand::thread_rng().gen_range(backoff / 2..=backoff)
+    } else {
+        backoff
+    };
+    Duration::from_millis(millis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay_ms: 1,
+            max_delay_ms: 4,
+            jitter: false,
+        }
+    }
+
+    #[test]
+    fn test_backoff_is_exponential_and_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            base_delay_ms: 100,
+            max_delay_ms: 300,
+            jitter: false,
+        };
+        let error = MiloError::Timeout;
+        assert_eq!(delay_for(&policy, 1, &error), Duration::from_millis(100));
+        assert_eq!(delay_for(&policy, 2, &error), Duration::from_millis(200));
+        assert_eq!(delay_for(&policy, 3, &error), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_retry_after_takes_precedence() {
+        let error = MiloError::RateLimited {
+            retry_after: Some(Duration::from_millis(2)),
+        };
+        assert_eq!(delay_for(&policy(3), 1, &error), Duration::from_millis(2));
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors() {
+        let (result, attempts) = with_retry(&policy(3), |attempt| async move {
+            if attempt < 3 {
+                Err(MiloError::Server {
+                    status: 502,
+                    retry_after: None,
+                })
+            } else {
+                Ok("done")
+            }
+        })
+        .await;
+
+        assert_eq!(result, Ok("done"));
+        assert_eq!(attempts, 3);
+    }
+
+    #[tokio::test]
+    async fn test_skips_retry_for_auth_errors() {
+        let (result, attempts) = with_retry(&policy(3), |_| async {
+            Err::<(), _>(MiloError::Unauthorized)
+        })
+        .await;
+
+        assert_eq!(result, Err(MiloError::Unauthorized));
+        assert_eq!(attempts, 1);
+    }
+}
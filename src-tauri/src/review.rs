@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, Runtime, WebviewUrl, WebviewWindowBuilder};
+use tokio::sync::oneshot;
+use tokio_util::sync::CancellationToken;
+
+use crate::error::MiloError;
+use crate::history::{compute_word_diff, TextDiff};
+use crate::state::AppState;
+
+/// Label of the floating window that shows a transform for review
+pub const REVIEW_WINDOW: &str = "review";
+
+/// The user's answer in the review window
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewDecision {
+    /// Write the result to the clipboard and record it
+    Accept,
+    /// Discard the result and leave the clipboard untouched
+    Reject,
+    /// Run the same prompt again
+    Retry,
+}
+
+/// The decision together with the candidate it applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReviewChoice {
+    pub decision: ReviewDecision,
+    /// Index into `ReviewPayload::candidates`
+    pub candidate: usize,
+}
+
+/// One rewrite offered for review
+#[derive(Debug, Clone, Serialize)]
+pub struct ReviewCandidate {
+    pub transformed_text: String,
+    pub diff: TextDiff,
+}
+
+/// What the review window shows; also sent as the `review-updated` event
+#[derive(Debug, Clone, Serialize)]
+pub struct ReviewPayload {
+    pub tone_name: String,
+    pub original_text: String,
+    pub candidates: Vec<ReviewCandidate>,
+}
+
+impl ReviewPayload {
+    pub fn new(tone_name: &str, original_text: &str, candidates: &[String]) -> Self {
+        Self {
+            tone_name: tone_name.to_string(),
+            original_text: original_text.to_string(),
+            candidates: candidates
+                .iter()
+                .map(|text| ReviewCandidate {
+                    transformed_text: text.clone(),
+                    diff: compute_word_diff(original_text, text),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A review waiting for the user's decision
+pub struct PendingReview {
+    payload: ReviewPayload,
+    responder: oneshot::Sender<ReviewChoice>,
+}
+
+/// Shows `payload` in the review window and waits for a decision. Closing the
+/// window counts as Reject; cancelling the transform closes the window.
+pub async fn request_review<R: Runtime>(
+    handle: &AppHandle<R>,
+    payload: ReviewPayload,
+    cancel: &CancellationToken,
+) -> Result<ReviewChoice, MiloError> {
+    let (responder, decision) = oneshot::channel();
+    let state = handle.state::<AppState>();
+    *state.pending_review.lock().unwrap() = Some(PendingReview {
+        payload: payload.clone(),
+        responder,
+    });
+
+    match handle.get_webview_window(REVIEW_WINDOW) {
+        Some(window) => {
+            let _ = handle.emit_to(REVIEW_WINDOW, "review-updated", payload);
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+        None => open_review_window(handle)?,
+    }
+
+    let choice = tokio::select! {
+        _ = cancel.cancelled() => {
+            state.pending_review.lock().unwrap().take();
+            close_review_window(handle);
+            return Err(MiloError::Cancelled);
+        }
+        choice = decision => choice.unwrap_or(ReviewChoice {
+            decision: ReviewDecision::Reject,
+            candidate: 0,
+        }),
+    };
+
+    // Retry keeps the window open so the next result replaces this one
+    if choice.decision != ReviewDecision::Retry {
+        close_review_window(handle);
+    }
+    Ok(choice)
+}
+
+fn open_review_window<R: Runtime>(handle: &AppHandle<R>) -> Result<(), MiloError> {
+    WebviewWindowBuilder::new(handle, REVIEW_WINDOW, WebviewUrl::App("index.html".into()))
+        .title("Milo - Review")
+        .inner_size(520.0, 420.0)
+        .always_on_top(true)
+        .resizable(true)
+        .center()
+        .focused(true)
+        .build()
+        .map_err(|e| MiloError::Api(format!("Failed to open review window: {}", e)))?;
+    Ok(())
+}
+
+fn close_review_window<R: Runtime>(handle: &AppHandle<R>) {
+    if let Some(window) = handle.get_webview_window(REVIEW_WINDOW) {
+        let _ = window.close();
+    }
+}
+
+/// Drops the pending review when its window goes away, which resolves it as Reject
+pub fn discard_pending_review<R: Runtime>(handle: &AppHandle<R>) {
+    handle
+        .state::<AppState>()
+        .pending_review
+        .lock()
+        .unwrap()
+        .take();
+}
+
+// The review window asks for its content once it has loaded
+#[tauri::command]
+pub fn get_pending_review(
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<ReviewPayload>, String> {
+    Ok(state
+        .pending_review
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|pending| pending.payload.clone()))
+}
+
+// `candidate` picks which rewrite Accept applies to; it defaults to the first
+#[tauri::command]
+pub fn resolve_review(
+    state: tauri::State<'_, AppState>,
+    decision: ReviewDecision,
+    candidate: Option<usize>,
+) -> Result<(), String> {
+    let mut pending_review = state.pending_review.lock().unwrap();
+    let candidate = candidate.unwrap_or(0);
+    match pending_review.as_ref() {
+        None => return Err("No transformation is waiting for review".to_string()),
+        Some(pending) if candidate >= pending.payload.candidates.len() => {
+            return Err(format!("Unknown candidate {}", candidate));
+        }
+        Some(_) => {}
+    }
+
+    let pending = pending_review.take().unwrap();
+    println!("Review resolved: {:?} candidate {}", decision, candidate);
+    let _ = pending.responder.send(ReviewChoice {
+        decision,
+        candidate,
+    });
+    Ok(())
+}
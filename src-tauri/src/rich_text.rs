@@ -0,0 +1,127 @@
+use pulldown_cmark::{html, Event, Options, Parser, Tag, TagEnd};
+
+use crate::error::MiloError;
+
+/// Converts clipboard HTML to the Markdown the model works on, keeping bold,
+/// links, headings and lists
+pub fn html_to_markdown(html: &str) -> Result<String, MiloError> {
+    htmd::convert(html).map_err(|e| MiloError::Clipboard(format!("Failed to convert HTML: {}", e)))
+}
+
+/// Renders the model's Markdown back to HTML for rich-text editors
+pub fn markdown_to_html(markdown: &str) -> String {
+    let mut output = String::with_capacity(markdown.len() * 3 / 2);
+    html::push_html(&mut output, Parser::new_ext(markdown, markdown_options()));
+    output
+}
+
+/// Markdown with its markup stripped, written as the plain-text alternative
+/// for apps that can't paste HTML. List numbers and link targets are kept
+/// since the text would lose meaning without them.
+pub fn markdown_to_plain_text(markdown: &str) -> String {
+    let mut output = String::with_capacity(markdown.len());
+    // Next number of each open list; None for bullet lists
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    // Target of each open link and where its text starts in the output
+    let mut links: Vec<(String, usize)> = Vec::new();
+
+    for event in Parser::new_ext(markdown, markdown_options()) {
+        match event {
+            Event::Text(text) | Event::Code(text) => output.push_str(&text),
+            Event::SoftBreak | Event::HardBreak => output.push('\n'),
+            Event::Start(Tag::List(start)) => {
+                // A nested list starts on the line after its parent item's text
+                if !lists.is_empty() && !output.ends_with('\n') {
+                    output.push('\n');
+                }
+                lists.push(start);
+            }
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+                if lists.is_empty() && !output.ends_with("\n\n") {
+                    output.push('\n');
+                }
+            }
+            Event::Start(Tag::Item) => {
+                output.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                match lists.last_mut() {
+                    Some(Some(number)) => {
+                        output.push_str(&format!("{}. ", number));
+                        *number += 1;
+                    }
+                    _ => output.push_str("- "),
+                }
+            }
+            Event::Start(Tag::Link { dest_url, .. }) => {
+                links.push((dest_url.to_string(), output.len()));
+            }
+            Event::End(TagEnd::Link) => {
+                // Autolinks already show their target as the text
+                if let Some((url, start)) = links.pop() {
+                    if !url.is_empty() && output[start..] != url {
+                        output.push_str(&format!(" ({})", url));
+                    }
+                }
+            }
+            Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::CodeBlock) => {
+                output.push_str("\n\n")
+            }
+            Event::End(TagEnd::Item) if !output.ends_with('\n') => output.push('\n'),
+            _ => {}
+        }
+    }
+
+    output.trim_end().to_string()
+}
+
+fn markdown_options() -> Options {
+    Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES | Options::ENABLE_TASKLISTS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_to_markdown_keeps_formatting() {
+        let markdown = html_to_markdown(
+            "<h1>Notes</h1><p>This is <b>bold</b> and a <a href=\"https://milo.app\">link</a>.</p>",
+        )
+        .unwrap();
+
+        assert!(markdown.contains("Notes"));
+        assert!(markdown.contains("**bold**"));
+        assert!(markdown.contains("[link](https://milo.app)"));
+    }
+
+    #[test]
+    fn test_markdown_to_html() {
+        let html = markdown_to_html("# Notes\n\nThis is **bold** and [a link](https://milo.app).");
+
+        assert!(html.contains("<h1>Notes</h1>"));
+        assert!(html.contains("<strong>bold</strong>"));
+        assert!(html.contains("<a href=\"https://milo.app\">a link</a>"));
+    }
+
+    #[test]
+    fn test_markdown_to_plain_text() {
+        let text = markdown_to_plain_text("# Notes\n\nSome **bold** text.\n\n- one\n- two");
+        assert_eq!(text, "Notes\n\nSome bold text.\n\n- one\n- two");
+    }
+
+    #[test]
+    fn test_plain_text_keeps_list_numbers() {
+        let text = markdown_to_plain_text("3. three\n4. four\n   - nested\n\nAfter the list");
+        assert_eq!(text, "3. three\n4. four\n  - nested\n\nAfter the list");
+    }
+
+    #[test]
+    fn test_plain_text_keeps_link_targets() {
+        let text =
+            markdown_to_plain_text("See [the docs](https://milo.app/docs) or <https://milo.app>.");
+        assert_eq!(
+            text,
+            "See the docs (https://milo.app/docs) or https://milo.app."
+        );
+    }
+}
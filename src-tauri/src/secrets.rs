@@ -0,0 +1,272 @@
+use std::{fs, num::NonZeroU32, path::PathBuf};
+
+use dirs::config_dir;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::settings::{api_key_file_path, litellm_api_key_file_path};
+
+const KEYRING_SERVICE: &str = "milo";
+const KEY_DERIVATION_SALT: &[u8] = b"milo-secret-store-v1";
+const KEY_DERIVATION_ROUNDS: u32 = 100_000;
+
+/// Keyring account of the provider API key
+pub const API_KEY: &str = "api_key";
+/// Keyring account of the LiteLLM usage key
+pub const LITELLM_API_KEY: &str = "litellm_api_key";
+
+/// Where keys are read from, so code that needs one can run against memory in tests
+pub trait SecretStore: Send + Sync {
+    fn load(&self, name: &str) -> Result<Option<String>, String>;
+}
+
+/// The OS keyring with its encrypted-file fallback, see `load_secret`
+pub struct SystemSecrets;
+
+impl SecretStore for SystemSecrets {
+    fn load(&self, name: &str) -> Result<Option<String>, String> {
+        load_secret(name)
+    }
+}
+
+/// Keeps secrets in memory, for tests that must not read the user's keys
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemorySecrets {
+    secrets: std::sync::Mutex<std::collections::HashMap<String, String>>,
+}
+
+#[cfg(test)]
+impl MemorySecrets {
+    pub fn with_secret(name: &str, value: &str) -> Self {
+        let store = Self::default();
+        store
+            .secrets
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), value.to_string());
+        store
+    }
+}
+
+#[cfg(test)]
+impl SecretStore for MemorySecrets {
+    fn load(&self, name: &str) -> Result<Option<String>, String> {
+        Ok(self.secrets.lock().unwrap().get(name).cloned())
+    }
+}
+
+/// Secret name of a named credential's key
+pub fn credential_secret_name(id: &str) -> String {
+    format!("credential_{}", id)
+}
+
+/// Masks all but a few characters at each end of a key, for showing in settings
+pub fn key_preview(key: &str) -> String {
+    if key.is_empty() {
+        String::new()
+    } else if key.len() <= 8 {
+        "****".to_string()
+    } else {
+        let visible_chars = std::cmp::min(4, key.len() / 3);
+        let prefix = &key[..visible_chars];
+        let suffix_start = key.len() - visible_chars;
+        let suffix = &key[suffix_start..];
+        format!("{}****{}", prefix, suffix)
+    }
+}
+
+/// Stores a secret in the OS keyring. Where there is no keyring, as on headless
+/// Linux without a secret service, it goes to a file encrypted with a local key.
+pub fn save_secret(name: &str, value: &str) -> Result<(), String> {
+    match keyring::Entry::new(KEYRING_SERVICE, name).and_then(|entry| entry.set_password(value)) {
+        Ok(()) => {
+            // A copy left from a time the keyring was unavailable would go stale
+            let _ = fs::remove_file(secret_file_path(name));
+            Ok(())
+        }
+        Err(e) => {
+            println!(
+                "⚠️ Keyring unavailable ({}), storing {} in an encrypted file",
+                e, name
+            );
+            write_secret_file(name, value)
+        }
+    }
+}
+
+/// Reads a secret from the keyring, falling back to the encrypted file
+pub fn load_secret(name: &str) -> Result<Option<String>, String> {
+    match keyring::Entry::new(KEYRING_SERVICE, name).and_then(|entry| entry.get_password()) {
+        Ok(value) => return Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => {}
+        Err(e) => println!("⚠️ Keyring unavailable ({}), reading {} from file", e, name),
+    }
+    read_secret_file(name)
+}
+
+/// Removes a secret from both the keyring and the fallback file
+pub fn delete_secret(name: &str) -> Result<(), String> {
+    match keyring::Entry::new(KEYRING_SERVICE, name).and_then(|entry| entry.delete_password()) {
+        Ok(()) | Err(keyring::Error::NoEntry) => {}
+        Err(e) => println!("⚠️ Failed to delete {} from keyring: {}", name, e),
+    }
+    match fs::remove_file(secret_file_path(name)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// Moves keys out of the plaintext files older versions wrote. A file is only
+/// deleted once its key is safely in the new store.
+pub fn migrate_plaintext_keys() {
+    for (name, path) in [
+        (API_KEY, api_key_file_path()),
+        (LITELLM_API_KEY, litellm_api_key_file_path()),
+    ] {
+        let Ok(key) = fs::read_to_string(&path) else {
+            continue;
+        };
+        if !key.is_empty() {
+            if let Err(e) = save_secret(name, &key) {
+                println!("❌ Failed to migrate {}: {}", name, e);
+                continue;
+            }
+        }
+        match fs::remove_file(&path) {
+            Ok(()) => println!("🔐 Moved {} out of {}", name, path.display()),
+            Err(e) => println!("⚠️ Failed to delete {}: {}", path.display(), e),
+        }
+    }
+}
+
+fn secret_file_path(name: &str) -> PathBuf {
+    let mut path = config_dir().unwrap_or_else(|| PathBuf::from("."));
+    path.push("milo");
+    path.push("secrets");
+    fs::create_dir_all(&path).unwrap();
+    path.push(format!("{}.enc", name));
+    path
+}
+
+fn write_secret_file(name: &str, value: &str) -> Result<(), String> {
+    let path = secret_file_path(name);
+    fs::write(&path, encrypt(&local_key(), value)?).map_err(|e| e.to_string())?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn read_secret_file(name: &str) -> Result<Option<String>, String> {
+    match fs::read(secret_file_path(name)) {
+        Ok(data) => decrypt(&local_key(), &data).map(Some),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// Derives the file key from the machine and user, so a copied file can't be
+// read elsewhere. It keeps keys out of plain sight, not safe from this user.
+fn local_key() -> [u8; 32] {
+    let machine_id = ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .iter()
+        .find_map(|path| fs::read_to_string(path).ok())
+        .unwrap_or_else(|| {
+            config_dir()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default()
+        });
+    let user = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default();
+    derive_key(&format!("{}:{}", machine_id.trim(), user))
+}
+
+fn derive_key(secret: &str) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        NonZeroU32::new(KEY_DERIVATION_ROUNDS).unwrap(),
+        KEY_DERIVATION_SALT,
+        secret.as_bytes(),
+        &mut key,
+    );
+    key
+}
+
+// Output is the random nonce followed by the AES-256-GCM ciphertext and tag
+fn encrypt(key: &[u8; 32], plaintext: &str) -> Result<Vec<u8>, String> {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| "Failed to generate nonce".to_string())?;
+
+    let mut sealed = plaintext.as_bytes().to_vec();
+    cipher(key)
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::empty(),
+            &mut sealed,
+        )
+        .map_err(|_| "Failed to encrypt secret".to_string())?;
+
+    let mut output = nonce.to_vec();
+    output.extend_from_slice(&sealed);
+    Ok(output)
+}
+
+fn decrypt(key: &[u8; 32], data: &[u8]) -> Result<String, String> {
+    if data.len() < NONCE_LEN {
+        return Err("Secret file is corrupt".to_string());
+    }
+    let (nonce, sealed) = data.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce)
+        .map_err(|_| "Secret file is corrupt".to_string())?;
+
+    let mut sealed = sealed.to_vec();
+    let plaintext = cipher(key)
+        .open_in_place(nonce, Aad::empty(), &mut sealed)
+        .map_err(|_| "Failed to decrypt secret; it may come from another machine".to_string())?;
+    String::from_utf8(plaintext.to_vec()).map_err(|e| e.to_string())
+}
+
+fn cipher(key: &[u8; 32]) -> LessSafeKey {
+    LessSafeKey::new(UnboundKey::new(&AES_256_GCM, key).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let key = derive_key("machine:user");
+        let sealed = encrypt(&key, "sk-secret").unwrap();
+
+        assert!(!String::from_utf8_lossy(&sealed).contains("sk-secret"));
+        assert_ne!(sealed, encrypt(&key, "sk-secret").unwrap());
+        assert_eq!(decrypt(&key, &sealed).unwrap(), "sk-secret");
+    }
+
+    #[test]
+    fn test_decrypt_rejects_other_key_and_tampering() {
+        let sealed = encrypt(&derive_key("machine:user"), "sk-secret").unwrap();
+        assert!(decrypt(&derive_key("other:user"), &sealed).is_err());
+
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(decrypt(&derive_key("machine:user"), &tampered).is_err());
+        assert!(decrypt(&derive_key("machine:user"), &sealed[..4]).is_err());
+    }
+
+    #[test]
+    fn test_key_preview() {
+        assert_eq!(key_preview(""), "");
+        assert_eq!(key_preview("sk-short"), "****");
+        assert_eq!(key_preview("sk-1234567890abcdef"), "sk-1****cdef");
+    }
+}
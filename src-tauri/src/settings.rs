@@ -1,19 +1,320 @@
 use anyhow::Result;
 use dirs::config_dir;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fs, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+};
+
+use crate::models::ModelInfo;
+use crate::template;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Settings {
     pub openai_model: String,
-    pub custom_prompts: HashMap<String, String>,
+    pub custom_prompts: HashMap<String, PromptDefinition>,
     #[serde(default)]
     pub prompt_order: Vec<String>,
     pub selected_tone: Option<String>,
     pub first_visit_complete: Option<bool>,
     pub shortcut_enabled: Option<bool>,
     pub shortcut_keys: Option<String>,
+    /// Global shortcut for undoing the last transform; disabled when unset
+    pub undo_shortcut_keys: Option<String>,
     pub theme: Option<String>,
+    pub streaming_enabled: Option<bool>,
+    /// Ignore HTML on the clipboard and only read and write plain text
+    pub plain_text_only: Option<bool>,
+    /// Show each result in a review window before it reaches the clipboard
+    pub preview_enabled: Option<bool>,
+    /// Copy the selection, transform it and paste the result over it
+    pub replace_selection: Option<bool>,
+    #[serde(default)]
+    pub prompt_providers: HashMap<String, ProviderSettings>,
+    /// Named API keys that prompts can select instead of the default key
+    #[serde(default)]
+    pub credentials: Vec<Credential>,
+    #[serde(default)]
+    pub retry_policy: RetryPolicy,
+    /// User-defined `{{name}}` placeholders for prompt templates
+    #[serde(default)]
+    pub template_variables: HashMap<String, String>,
+    /// Tones that run several prompts in order, keyed by tone name
+    #[serde(default)]
+    pub pipelines: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub chunking: ChunkingSettings,
+    #[serde(default)]
+    pub concurrency_mode: ConcurrencyMode,
+    #[serde(default)]
+    pub timeouts: TimeoutSettings,
+    #[serde(default)]
+    pub clipboard_restore: ClipboardRestoreSettings,
+    #[serde(default)]
+    pub key_check: KeyCheckSettings,
+    /// Which one-time migrations the stored settings have been through,
+    /// see `SETTINGS_VERSION`
+    #[serde(default)]
+    pub settings_version: u32,
+}
+
+/// Settings saved before version 1 may hold prompts with a literal `{{`
+pub const SETTINGS_VERSION: u32 = 1;
+
+/// Upper bound for `PromptDefinition::candidates`, which multiplies the cost of a transform
+pub const MAX_CANDIDATES: u32 = 5;
+
+/// How long clipboard text is split up before it is sent
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ChunkingSettings {
+    /// Texts longer than this many characters are split into chunks
+    pub chunk_chars: usize,
+    /// Chunks transformed at the same time
+    pub max_concurrency: usize,
+    /// Texts longer than this are refused outright
+    pub max_input_chars: usize,
+    /// Refuse texts longer than `chunk_chars` instead of chunking them
+    pub refuse_oversized: bool,
+}
+
+impl Default for ChunkingSettings {
+    fn default() -> Self {
+        Self {
+            chunk_chars: 6000,
+            max_concurrency: 3,
+            max_input_chars: 200_000,
+            refuse_oversized: false,
+        }
+    }
+}
+
+/// Limits on how long a provider call may take
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct TimeoutSettings {
+    pub connect_timeout_ms: u64,
+    /// Whole request including the response body, streamed or not
+    pub request_timeout_ms: u64,
+}
+
+impl Default for TimeoutSettings {
+    fn default() -> Self {
+        Self {
+            connect_timeout_ms: 10_000,
+            request_timeout_ms: 60_000,
+        }
+    }
+}
+
+/// Putting back what was on the clipboard before a transform
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ClipboardRestoreSettings {
+    /// Only leave the result on the clipboard for a while, then restore the previous contents
+    pub enabled: bool,
+    /// How long the result stays on the clipboard
+    pub delay_ms: u64,
+}
+
+impl Default for ClipboardRestoreSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            delay_ms: 15_000,
+        }
+    }
+}
+
+/// Checking the LiteLLM key and what is left of its budget
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct KeyCheckSettings {
+    /// Warn in the tray when fewer dollars than this are left
+    pub low_budget_threshold: f64,
+    /// How long a check is reused before the proxy is asked again
+    pub cache_minutes: u64,
+}
+
+impl Default for KeyCheckSettings {
+    fn default() -> Self {
+        Self {
+            low_budget_threshold: 1.0,
+            cache_minutes: 10,
+        }
+    }
+}
+
+/// How failed provider calls are retried
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one; 1 disables retries
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    /// Randomizes each delay between half and the full backoff
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 500,
+            max_delay_ms: 8000,
+            jitter: true,
+        }
+    }
+}
+
+/// A tone's prompt together with the sampling parameters it is sent with.
+/// Unset parameters fall back to the provider's defaults.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(from = "PromptDefinitionRepr")]
+pub struct PromptDefinition {
+    pub prompt: String,
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub stop: Option<Vec<String>>,
+    /// Alternative rewrites to generate and pick from; unset means one
+    pub candidates: Option<u32>,
+}
+
+impl PromptDefinition {
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn candidate_count(&self) -> u32 {
+        self.candidates.unwrap_or(1).max(1)
+    }
+}
+
+/// Accepts both the structured form and the plain prompt strings that
+/// `custom_prompts` held before prompts had parameters
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PromptDefinitionRepr {
+    Legacy(String),
+    Structured {
+        prompt: String,
+        #[serde(default)]
+        model: Option<String>,
+        #[serde(default)]
+        temperature: Option<f32>,
+        #[serde(default)]
+        top_p: Option<f32>,
+        #[serde(default)]
+        max_tokens: Option<u32>,
+        #[serde(default)]
+        stop: Option<Vec<String>>,
+        #[serde(default)]
+        candidates: Option<u32>,
+    },
+}
+
+impl From<PromptDefinitionRepr> for PromptDefinition {
+    fn from(repr: PromptDefinitionRepr) -> Self {
+        match repr {
+            PromptDefinitionRepr::Legacy(prompt) => PromptDefinition::new(prompt),
+            PromptDefinitionRepr::Structured {
+                prompt,
+                model,
+                temperature,
+                top_p,
+                max_tokens,
+                stop,
+                candidates,
+            } => PromptDefinition {
+                prompt,
+                model,
+                temperature,
+                top_p,
+                max_tokens,
+                stop,
+                candidates,
+            },
+        }
+    }
+}
+
+/// Which backend a prompt is sent to.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    /// The Milo LiteLLM proxy configured in `config.json`
+    #[default]
+    Litellm,
+    /// Any endpoint that speaks the OpenAI chat completions API
+    OpenaiCompatible,
+    /// Anthropic-style `/v1/messages` API
+    Anthropic,
+    /// Ollama-compatible local server
+    Ollama,
+}
+
+impl ProviderKind {
+    /// Local servers run without a key; every other backend rejects requests without one
+    pub fn needs_api_key(self) -> bool {
+        self != ProviderKind::Ollama
+    }
+}
+
+/// What happens when a transform is triggered while another one is running
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ConcurrencyMode {
+    /// Ignore the new request
+    #[default]
+    Reject,
+    /// Run the new request once the current one finishes
+    Queue,
+    /// Cancel the current request and run the new one
+    Replace,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ProviderSettings {
+    #[serde(default)]
+    pub kind: ProviderKind,
+    /// Overrides the provider's default endpoint
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Overrides the provider's default model
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Id of the credential whose key, provider and endpoint are used instead
+    /// of the default key
+    #[serde(default)]
+    pub credential: Option<String>,
+}
+
+impl ProviderSettings {
+    /// Whether requests go to the default LiteLLM proxy, whose model catalog Milo keeps
+    pub fn uses_default_proxy(&self) -> bool {
+        self.kind == ProviderKind::Litellm && self.base_url.is_none() && self.credential.is_none()
+    }
+}
+
+/// An API key saved under a name, together with the endpoint it belongs to.
+/// The key itself is kept in the secret store, never in the settings file.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Credential {
+    /// Stable id that prompts refer to, so renaming doesn't break them
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub kind: ProviderKind,
+    /// The provider's default endpoint when unset
+    #[serde(default)]
+    pub base_url: Option<String>,
 }
 
 impl Default for Settings {
@@ -21,7 +322,7 @@ impl Default for Settings {
         let mut custom_prompts = HashMap::new();
         custom_prompts.insert(
             "Improve Writing".to_string(),
-            "Improve this text while maintaining its meaning:".to_string(),
+            PromptDefinition::new("Improve this text while maintaining its meaning:"),
         );
         Self {
             openai_model: crate::config::CONFIG.default_model.clone(),
@@ -31,16 +332,43 @@ impl Default for Settings {
             first_visit_complete: Some(false),
             shortcut_enabled: Some(true),
             shortcut_keys: Some("meta+KeyM".to_string()),
+            undo_shortcut_keys: None,
             theme: Some("light".to_string()),
+            streaming_enabled: Some(true),
+            plain_text_only: Some(false),
+            preview_enabled: Some(false),
+            replace_selection: Some(false),
+            prompt_providers: HashMap::new(),
+            credentials: Vec::new(),
+            retry_policy: RetryPolicy::default(),
+            template_variables: HashMap::new(),
+            pipelines: HashMap::new(),
+            chunking: ChunkingSettings::default(),
+            concurrency_mode: ConcurrencyMode::default(),
+            timeouts: TimeoutSettings::default(),
+            clipboard_restore: ClipboardRestoreSettings::default(),
+            key_check: KeyCheckSettings::default(),
+            settings_version: SETTINGS_VERSION,
         }
     }
 }
 
 impl Settings {
     pub fn load() -> Self {
-        let mut settings: Settings = fs::read_to_string(settings_file_path())
+        let raw: Option<serde_json::Value> = fs::read_to_string(settings_file_path())
             .ok()
-            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok());
+
+        // Prompts used to be stored as plain strings; they deserialize into
+        // `PromptDefinition` directly but get rewritten in the structured form
+        let mut needs_save = raw
+            .as_ref()
+            .and_then(|value| value.get("custom_prompts"))
+            .and_then(|prompts| prompts.as_object())
+            .is_some_and(|prompts| prompts.values().any(|prompt| prompt.is_string()));
+
+        let mut settings: Settings = raw
+            .and_then(|value| serde_json::from_value(value).ok())
             .unwrap_or_default();
 
         // Ensure backward compatibility: populate prompt_order if missing or empty
@@ -52,14 +380,147 @@ impl Settings {
                 prompt_names.insert(0, improve_writing);
             }
             settings.prompt_order = prompt_names;
+            needs_save = true;
+        }
+
+        if settings.migrate() {
+            needs_save = true;
+        }
 
-            // Save the updated settings immediately to persist the migration
+        // Save the updated settings immediately to persist the migration
+        if needs_save {
             let _ = settings.save();
         }
 
         settings
     }
 
+    // One-time rewrites of settings saved by older versions; returns whether any ran
+    fn migrate(&mut self) -> bool {
+        if self.settings_version >= SETTINGS_VERSION {
+            return false;
+        }
+        if self.settings_version < 1 {
+            self.escape_legacy_templates();
+        }
+        self.settings_version = SETTINGS_VERSION;
+        true
+    }
+
+    // Prompts written before templating may contain a literal `{{`, which is
+    // escaped so it keeps reaching the model as written
+    fn escape_legacy_templates(&mut self) {
+        for (tone, definition) in self.custom_prompts.iter_mut() {
+            let escaped = template::escape_invalid(&definition.prompt);
+            if escaped != definition.prompt {
+                println!("🔧 Escaping literal '{{{{' in prompt '{}'", tone);
+                definition.prompt = escaped;
+            }
+        }
+    }
+
+    /// Prompts whose templates would fail at transform time, such as ones
+    /// naming a variable that has since been deleted
+    pub fn template_problems(&self) -> Vec<String> {
+        let mut problems: Vec<String> = self
+            .custom_prompts
+            .iter()
+            .filter_map(|(tone, definition)| {
+                template::validate(&definition.prompt, &self.template_variables)
+                    .err()
+                    .map(|e| format!("Tone '{}': {}", tone, e))
+            })
+            .collect();
+        problems.sort();
+        problems
+    }
+
+    /// Rejects settings that would only fail later at transform time
+    pub fn validate(&self) -> Result<(), String> {
+        for name in self.template_variables.keys() {
+            template::validate_variable_name(name)?;
+        }
+        for (tone, definition) in &self.custom_prompts {
+            template::validate(&definition.prompt, &self.template_variables)
+                .map_err(|e| format!("Tone '{}': {}", tone, e))?;
+            if definition.candidate_count() > MAX_CANDIDATES {
+                return Err(format!(
+                    "Tone '{}' asks for more than {} candidates",
+                    tone, MAX_CANDIDATES
+                ));
+            }
+        }
+        for (name, steps) in &self.pipelines {
+            if self.custom_prompts.contains_key(name) {
+                return Err(format!("Pipeline '{}' has the same name as a prompt", name));
+            }
+            if steps.is_empty() {
+                return Err(format!("Pipeline '{}' has no steps", name));
+            }
+            if let Some(step) = steps
+                .iter()
+                .find(|step| !self.custom_prompts.contains_key(*step))
+            {
+                return Err(format!(
+                    "Pipeline '{}' uses unknown prompt '{}'",
+                    name, step
+                ));
+            }
+        }
+        if self.timeouts.connect_timeout_ms == 0 || self.timeouts.request_timeout_ms == 0 {
+            return Err("Timeouts must be greater than zero".to_string());
+        }
+        if self.clipboard_restore.enabled && self.clipboard_restore.delay_ms == 0 {
+            return Err("Clipboard restore delay must be greater than zero".to_string());
+        }
+        let mut credential_names = HashSet::new();
+        for credential in &self.credentials {
+            let name = credential.name.trim();
+            if name.is_empty() {
+                return Err("Credential names must not be empty".to_string());
+            }
+            if !credential_names.insert(name.to_lowercase()) {
+                return Err(format!(
+                    "There is more than one credential named '{}'",
+                    name
+                ));
+            }
+        }
+        for (prompt, provider) in &self.prompt_providers {
+            if let Some(id) = &provider.credential {
+                if self.credential(id).is_none() {
+                    return Err(format!(
+                        "Prompt '{}' uses a credential that no longer exists",
+                        prompt
+                    ));
+                }
+            }
+        }
+        if self.key_check.low_budget_threshold < 0.0 {
+            return Err("Low budget threshold must not be negative".to_string());
+        }
+        Ok(())
+    }
+
+    /// Prompt keys to run for a tone: the pipeline's steps, or the prompt itself
+    pub fn steps_for_tone(&self, tone: &str) -> Result<Vec<String>, String> {
+        if let Some(steps) = self.pipelines.get(tone) {
+            if let Some(missing) = steps
+                .iter()
+                .find(|step| !self.custom_prompts.contains_key(*step))
+            {
+                return Err(format!("Prompt not found for key: {}", missing));
+            }
+            return Ok(steps.clone());
+        }
+
+        if self.custom_prompts.contains_key(tone) {
+            Ok(vec![tone.to_string()])
+        } else {
+            Err(format!("Prompt not found for key: {}", tone))
+        }
+    }
+
     pub fn save(&self) -> Result<(), String> {
         let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
         fs::write(settings_file_path(), json).map_err(|e| e.to_string())
@@ -78,6 +539,104 @@ impl Settings {
     pub fn get_theme(&self) -> String {
         self.theme.clone().unwrap_or_else(|| "light".to_string())
     }
+
+    pub fn is_streaming_enabled(&self) -> bool {
+        self.streaming_enabled.unwrap_or(true)
+    }
+
+    pub fn is_plain_text_only(&self) -> bool {
+        self.plain_text_only.unwrap_or(false)
+    }
+
+    pub fn is_preview_enabled(&self) -> bool {
+        self.preview_enabled.unwrap_or(false)
+    }
+
+    pub fn is_replace_selection_enabled(&self) -> bool {
+        self.replace_selection.unwrap_or(false)
+    }
+
+    /// Rejects models the LiteLLM proxy doesn't offer, for the prompts sent to
+    /// it. Models already used in `previous` pass, so one the proxy dropped
+    /// doesn't block saving unrelated changes.
+    pub fn validate_models(
+        &self,
+        catalog: &[ModelInfo],
+        previous: &Settings,
+    ) -> Result<(), String> {
+        for (used_by, model) in self.new_proxy_models(previous) {
+            if !catalog.iter().any(|info| info.id == model) {
+                return Err(format!("{} uses unknown model '{}'", used_by, model));
+            }
+        }
+        Ok(())
+    }
+
+    /// Models sent to the default LiteLLM proxy that `previous` didn't use yet,
+    /// with what selects each of them
+    pub fn new_proxy_models(&self, previous: &Settings) -> Vec<(String, String)> {
+        let previous_models = previous.proxy_models();
+        self.proxy_models()
+            .into_iter()
+            .filter(|(_, model)| {
+                !previous_models
+                    .iter()
+                    .any(|(_, previous)| previous == model)
+            })
+            .collect()
+    }
+
+    // Models sent to the default LiteLLM proxy, with what selects each of them
+    fn proxy_models(&self) -> Vec<(String, String)> {
+        let mut models = Vec::new();
+        if !self.openai_model.is_empty() {
+            models.push(("The default model".to_string(), self.openai_model.clone()));
+        }
+        for (tone, definition) in &self.custom_prompts {
+            let provider = self.provider_for_prompt(tone);
+            if !provider.uses_default_proxy() {
+                continue;
+            }
+            for model in [&definition.model, &provider.model].into_iter().flatten() {
+                models.push((format!("Tone '{}'", tone), model.clone()));
+            }
+        }
+        models
+    }
+
+    pub fn credential(&self, id: &str) -> Option<&Credential> {
+        self.credentials
+            .iter()
+            .find(|credential| credential.id == id)
+    }
+
+    /// Provider for a prompt, falling back to the LiteLLM proxy with `openai_model`.
+    /// A selected credential decides the provider and, unless the prompt
+    /// overrides it, the endpoint.
+    pub fn provider_for_prompt(&self, prompt_key: &str) -> ProviderSettings {
+        let mut provider = self
+            .prompt_providers
+            .get(prompt_key)
+            .cloned()
+            .unwrap_or_default();
+        if let Some(credential) = provider
+            .credential
+            .as_deref()
+            .and_then(|id| self.credential(id))
+        {
+            provider.kind = credential.kind;
+            if provider.base_url.is_none() {
+                provider.base_url = credential.base_url.clone();
+            }
+        }
+        if provider.kind == ProviderKind::Litellm
+            && provider.model.is_none()
+            && !self.openai_model.is_empty()
+        {
+            provider.model = Some(self.openai_model.clone());
+        }
+        provider
+    }
 }
 
 pub fn settings_file_path() -> PathBuf {
@@ -88,6 +647,7 @@ pub fn settings_file_path() -> PathBuf {
     path
 }
 
+/// Plaintext key file of older versions, only read to migrate it to the keyring
 pub fn api_key_file_path() -> PathBuf {
     let mut path = config_dir().unwrap_or_else(|| PathBuf::from("."));
     path.push("milo");
@@ -96,6 +656,7 @@ pub fn api_key_file_path() -> PathBuf {
     path
 }
 
+/// Plaintext LiteLLM key file of older versions, only read to migrate it
 pub fn litellm_api_key_file_path() -> PathBuf {
     let mut path = config_dir().unwrap_or_else(|| PathBuf::from("."));
     path.push("milo");
@@ -103,3 +664,198 @@ pub fn litellm_api_key_file_path() -> PathBuf {
     path.push("litellm_api_key.txt");
     path
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps_for_tone() {
+        let mut settings = Settings::default();
+        settings.custom_prompts.insert(
+            "Make concise".to_string(),
+            PromptDefinition::new("Shorten:"),
+        );
+        settings.pipelines.insert(
+            "Polish".to_string(),
+            vec!["Improve Writing".to_string(), "Make concise".to_string()],
+        );
+
+        assert_eq!(
+            settings.steps_for_tone("Polish").unwrap(),
+            vec!["Improve Writing", "Make concise"]
+        );
+        assert_eq!(
+            settings.steps_for_tone("Make concise").unwrap(),
+            vec!["Make concise"]
+        );
+        assert!(settings.steps_for_tone("Missing").is_err());
+        assert!(settings.validate().is_ok());
+
+        settings
+            .pipelines
+            .insert("Broken".to_string(), vec!["Missing".to_string()]);
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_legacy_string_prompts_deserialize() {
+        let json = r#"{
+            "openai_model": "gpt-4o-mini",
+            "custom_prompts": {
+                "Fix typos": "Fix the typos:",
+                "Translate": { "prompt": "Translate to Japanese:", "model": "gpt-4o", "temperature": 0.2 }
+            },
+            "selected_tone": "Fix typos",
+            "first_visit_complete": true,
+            "shortcut_enabled": true,
+            "shortcut_keys": null,
+            "theme": null
+        }"#;
+        let settings: Settings = serde_json::from_str(json).unwrap();
+
+        assert_eq!(
+            settings.custom_prompts["Fix typos"],
+            PromptDefinition::new("Fix the typos:")
+        );
+        let translate = &settings.custom_prompts["Translate"];
+        assert_eq!(translate.prompt, "Translate to Japanese:");
+        assert_eq!(translate.model.as_deref(), Some("gpt-4o"));
+        assert_eq!(translate.temperature, Some(0.2));
+        assert_eq!(translate.max_tokens, None);
+
+        // Re-serialized prompts use the structured form
+        let value = serde_json::to_value(&settings).unwrap();
+        assert_eq!(
+            value["custom_prompts"]["Fix typos"]["prompt"],
+            "Fix the typos:"
+        );
+    }
+
+    #[test]
+    fn test_legacy_templates_are_escaped() {
+        let json = r#"{
+            "openai_model": "gpt-4o-mini",
+            "custom_prompts": {
+                "JSON": "Reply to {{app_name}} as {{\"reply\": \"...\"}}:",
+                "Team": "Rewrite for the {{team}} team:"
+            },
+            "selected_tone": "JSON",
+            "first_visit_complete": true,
+            "shortcut_enabled": true,
+            "shortcut_keys": null,
+            "theme": null
+        }"#;
+        let mut settings: Settings = serde_json::from_str(json).unwrap();
+        assert_eq!(settings.settings_version, 0);
+
+        assert!(settings.migrate());
+        assert_eq!(
+            settings.custom_prompts["JSON"].prompt,
+            "Reply to {{app_name}} as \\{{\"reply\": \"...\"}}:"
+        );
+        // The placeholder of a deleted variable is reported, not escaped away
+        assert_eq!(
+            settings.custom_prompts["Team"].prompt,
+            "Rewrite for the {{team}} team:"
+        );
+        assert_eq!(
+            settings.template_problems(),
+            vec!["Tone 'Team': Unknown placeholder '{{team}}'".to_string()]
+        );
+
+        settings
+            .template_variables
+            .insert("team".to_string(), "Docs".to_string());
+        assert!(settings.template_problems().is_empty());
+        assert!(settings.validate().is_ok());
+
+        // Migrated settings are never rewritten again
+        let broken = PromptDefinition::new("Reply as {{ \"a\": 1 }}");
+        settings
+            .custom_prompts
+            .insert("JSON".to_string(), broken.clone());
+        assert!(!settings.migrate());
+        assert_eq!(settings.custom_prompts["JSON"], broken);
+    }
+
+    #[test]
+    fn test_prompt_uses_selected_credential() {
+        let mut settings = Settings {
+            credentials: vec![Credential {
+                id: "work".to_string(),
+                name: "Work proxy".to_string(),
+                kind: ProviderKind::OpenaiCompatible,
+                base_url: Some("https://proxy.example.com/v1".to_string()),
+            }],
+            ..Default::default()
+        };
+        settings.prompt_providers.insert(
+            "Improve Writing".to_string(),
+            ProviderSettings {
+                credential: Some("work".to_string()),
+                ..Default::default()
+            },
+        );
+
+        let provider = settings.provider_for_prompt("Improve Writing");
+        assert_eq!(provider.kind, ProviderKind::OpenaiCompatible);
+        assert_eq!(
+            provider.base_url.as_deref(),
+            Some("https://proxy.example.com/v1")
+        );
+        assert_eq!(provider.credential.as_deref(), Some("work"));
+        assert!(settings.validate().is_ok());
+
+        settings.credentials.push(Credential {
+            id: "other".to_string(),
+            name: "work proxy ".to_string(),
+            kind: ProviderKind::Litellm,
+            base_url: None,
+        });
+        assert!(settings.validate().is_err());
+
+        settings.credentials.clear();
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_models() {
+        let catalog = vec![ModelInfo {
+            id: "gpt-4o-mini".to_string(),
+            context_window: Some(128_000),
+        }];
+        let previous = Settings::default();
+        let mut settings = Settings::default();
+        assert!(settings.validate_models(&catalog, &previous).is_ok());
+        // Unrelated changes don't need the catalog at all
+        settings.theme = Some("dark".to_string());
+        assert!(settings.new_proxy_models(&previous).is_empty());
+
+        settings
+            .custom_prompts
+            .get_mut("Improve Writing")
+            .unwrap()
+            .model = Some("gpt-5-turbo".to_string());
+        assert!(settings.validate_models(&catalog, &previous).is_err());
+        assert_eq!(
+            settings.new_proxy_models(&previous),
+            vec![(
+                "Tone 'Improve Writing'".to_string(),
+                "gpt-5-turbo".to_string()
+            )]
+        );
+        // Already in use before, so it doesn't block saving
+        assert!(settings.validate_models(&catalog, &settings).is_ok());
+
+        // Prompts sent elsewhere have their own models
+        settings.prompt_providers.insert(
+            "Improve Writing".to_string(),
+            ProviderSettings {
+                kind: ProviderKind::Ollama,
+                ..Default::default()
+            },
+        );
+        assert!(settings.validate_models(&catalog, &previous).is_ok());
+    }
+}
@@ -6,14 +6,43 @@ use crate::state::AppState;
 
 // Global state to track the currently registered shortcut
 static CURRENT_SHORTCUT: Mutex<Option<Shortcut>> = Mutex::new(None);
+// Optional shortcut that undoes the last transformation
+static UNDO_SHORTCUT: Mutex<Option<Shortcut>> = Mutex::new(None);
 
 pub fn register_shortcuts(app_handle: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
     println!("🔧 Starting shortcut registration...");
     register_transform_shortcut_from_settings(app_handle)?;
+    register_undo_shortcut_from_settings(app_handle);
     println!("✅ Shortcut registration completed");
     Ok(())
 }
 
+/// Whether a pressed shortcut is the undo shortcut rather than the transform one
+pub fn is_undo_shortcut(shortcut: &Shortcut) -> bool {
+    UNDO_SHORTCUT.lock().unwrap().as_ref() == Some(shortcut)
+}
+
+// A broken undo shortcut shouldn't stop the app from starting, so failures are only logged
+fn register_undo_shortcut_from_settings(app_handle: &AppHandle) {
+    let state = app_handle.state::<AppState>();
+    let settings = tauri::async_runtime::block_on(state.settings.lock());
+    let Some(shortcut_str) = settings.undo_shortcut_keys.clone() else {
+        println!("📝 No undo shortcut configured");
+        return;
+    };
+    drop(settings);
+
+    let result = parse_shortcut(&shortcut_str)
+        .and_then(|shortcut| register_undo_shortcut(app_handle, shortcut).map(|_| shortcut));
+    match result {
+        Ok(shortcut) => *UNDO_SHORTCUT.lock().unwrap() = Some(shortcut),
+        Err(e) => println!(
+            "❌ Failed to register undo shortcut '{}': {}",
+            shortcut_str, e
+        ),
+    }
+}
+
 fn register_transform_shortcut_from_settings(
     app_handle: &AppHandle,
 ) -> Result<(), Box<dyn std::error::Error>> {
@@ -72,6 +101,28 @@ fn register_transform_shortcut(
     }
 }
 
+fn register_undo_shortcut(
+    app_handle: &AppHandle,
+    shortcut: Shortcut,
+) -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(desktop)]
+    {
+        println!("🔄 Registering undo shortcut: {:?}", shortcut);
+        app_handle.global_shortcut().register(shortcut).map_err(|e| {
+            let error_msg = format!("Failed to register undo shortcut {:?}: {}. This shortcut may already be in use by another application.", shortcut, e);
+            println!("❌ {}", error_msg);
+            error_msg
+        })?;
+        println!("✅ Undo shortcut registered with system: {:?}", shortcut);
+        Ok(())
+    }
+    #[cfg(not(desktop))]
+    {
+        println!("⚠️  Not on desktop platform, skipping undo shortcut registration");
+        Ok(())
+    }
+}
+
 fn parse_shortcut(shortcut_str: &str) -> Result<Shortcut, Box<dyn std::error::Error>> {
     println!("🔍 Parsing shortcut: '{}'", shortcut_str);
     let parts: Vec<&str> = shortcut_str.split('+').collect();
@@ -185,11 +236,14 @@ pub async fn update_shortcut(
 
     // Validate shortcut format
     println!("🔍 Validating shortcut format...");
-    parse_shortcut(&shortcut_keys).map_err(|e| {
+    let new_shortcut = parse_shortcut(&shortcut_keys).map_err(|e| {
         let error_msg = format!("Invalid shortcut format: {}", e);
         println!("❌ Validation failed: {}", error_msg);
         error_msg
     })?;
+    if is_undo_shortcut(&new_shortcut) {
+        return Err("The transform shortcut must differ from the undo shortcut".to_string());
+    }
     println!("✅ Shortcut format validated");
 
     // Unregister current shortcut
@@ -231,6 +285,64 @@ pub async fn update_shortcut(
     Ok(())
 }
 
+#[tauri::command]
+pub async fn get_undo_shortcut(
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<String>, String> {
+    let settings = state.settings.lock().await;
+    Ok(settings.undo_shortcut_keys.clone())
+}
+
+// Sets the undo shortcut, or disables it when `shortcut_keys` is None
+#[tauri::command]
+pub async fn update_undo_shortcut(
+    app_handle: AppHandle,
+    state: tauri::State<'_, AppState>,
+    shortcut_keys: Option<String>,
+) -> Result<(), String> {
+    println!(
+        "🔄 Update undo shortcut request received: {:?}",
+        shortcut_keys
+    );
+
+    let shortcut = shortcut_keys
+        .as_deref()
+        .map(parse_shortcut)
+        .transpose()
+        .map_err(|e| format!("Invalid shortcut format: {}", e))?;
+    if shortcut.is_some() && shortcut == *CURRENT_SHORTCUT.lock().unwrap() {
+        return Err("The undo shortcut must differ from the transform shortcut".to_string());
+    }
+
+    #[cfg(desktop)]
+    {
+        let previous = UNDO_SHORTCUT.lock().unwrap().take();
+        if let Some(previous) = previous {
+            app_handle
+                .global_shortcut()
+                .unregister(previous)
+                .map_err(|e| format!("Failed to unregister undo shortcut: {}", e))?;
+        }
+    }
+
+    {
+        let mut settings = state.settings.lock().await;
+        settings.undo_shortcut_keys = shortcut_keys;
+        settings
+            .save()
+            .map_err(|e| format!("Failed to save settings: {}", e))?;
+    }
+
+    if let Some(shortcut) = shortcut {
+        register_undo_shortcut(&app_handle, shortcut)
+            .map_err(|e| format!("Failed to register undo shortcut: {}", e))?;
+        *UNDO_SHORTCUT.lock().unwrap() = Some(shortcut);
+    }
+    println!("✅ Undo shortcut updated");
+
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn unregister_shortcut(app_handle: AppHandle) -> Result<(), String> {
     println!("🔄 Unregister shortcut request received");
@@ -1,17 +1,160 @@
-use crate::settings::Settings;
-use std::sync::Mutex;
-use tokio::sync::Mutex as TokioMutex;
+use crate::cache::TtlCache;
+use crate::clipboard::{ArboardClipboard, ClipboardBackend, PendingRestore};
+use crate::error::MiloError;
+use crate::key_status::KeyStatus;
+use crate::models::ModelInfo;
+use crate::providers::TransformProvider;
+use crate::review::PendingReview;
+use crate::settings::{ConcurrencyMode, Settings};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Runtime};
+use tokio::sync::{Mutex as TokioMutex, MutexGuard as TokioMutexGuard};
+use tokio_util::sync::CancellationToken;
 
 pub struct AppState {
     pub settings: TokioMutex<Settings>,
-    pub is_transforming: Mutex<bool>,
+    pub transforms: TransformCoordinator,
+    /// History entries already undone since the last transformation; the next
+    /// undo restores the entry at this index
+    pub undo_position: Mutex<usize>,
+    /// Transform result waiting in the review window
+    pub pending_review: Mutex<Option<PendingReview>>,
+    /// Clipboard contents waiting to be put back after a transform
+    pub pending_restore: Mutex<Option<PendingRestore>>,
+    /// Where transforms read and write the clipboard
+    pub clipboard: Arc<dyn ClipboardBackend>,
+    /// Used for every step instead of the configured providers when set
+    pub provider_override: Option<Arc<dyn TransformProvider>>,
+    /// Last result of checking the LiteLLM key, keyed by the key
+    pub key_status: TtlCache<KeyStatus>,
+    /// Models offered by the LiteLLM proxy, keyed by its base URL
+    pub model_catalog: TtlCache<Vec<ModelInfo>>,
+    /// Last failure to list the proxy's models, keyed by its base URL
+    pub model_catalog_error: TtlCache<MiloError>,
+    /// Where transformations are recorded
+    pub history_path: PathBuf,
 }
 
 impl AppState {
     pub fn new(settings: Settings) -> Self {
+        Self::with_clipboard(settings, Arc::new(ArboardClipboard::default()))
+    }
+
+    pub fn with_clipboard(settings: Settings, clipboard: Arc<dyn ClipboardBackend>) -> Self {
         Self {
             settings: TokioMutex::new(settings),
-            is_transforming: Mutex::new(false),
+            transforms: TransformCoordinator::default(),
+            undo_position: Mutex::new(0),
+            pending_review: Mutex::new(None),
+            pending_restore: Mutex::new(None),
+            clipboard,
+            provider_override: None,
+            key_status: TtlCache::default(),
+            model_catalog: TtlCache::default(),
+            model_catalog_error: TtlCache::default(),
+            history_path: crate::history::history_file_path(),
         }
     }
 }
+
+/// Payload of the `transform-started` and `transform-finished` events
+#[derive(Debug, Clone, Serialize)]
+pub struct TransformStatus {
+    pub tone_name: String,
+}
+
+/// Makes sure only one transformation touches the clipboard at a time
+#[derive(Default)]
+pub struct TransformCoordinator {
+    slot: TokioMutex<()>,
+    is_transforming: Mutex<bool>,
+    /// Cancels the running transformation, if any
+    current: Mutex<Option<CancellationToken>>,
+    /// Bumped by every replacing request so older waiters know they were superseded
+    generation: AtomicU64,
+}
+
+impl TransformCoordinator {
+    /// Waits for (or refuses) a turn to transform according to `mode`. The
+    /// returned guard holds the turn until it is dropped.
+    pub async fn acquire<R: Runtime>(
+        &self,
+        mode: ConcurrencyMode,
+        handle: &AppHandle<R>,
+        tone_name: &str,
+    ) -> Result<TransformGuard<'_, R>, MiloError> {
+        let slot = match mode {
+            ConcurrencyMode::Reject => self.slot.try_lock().map_err(|_| MiloError::Busy)?,
+            ConcurrencyMode::Queue => self.slot.lock().await,
+            ConcurrencyMode::Replace => {
+                let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+                self.cancel_current();
+                let slot = self.slot.lock().await;
+                if self.generation.load(Ordering::SeqCst) != generation {
+                    // A newer request replaced this one while it was waiting
+                    return Err(MiloError::Cancelled);
+                }
+                slot
+            }
+        };
+
+        let cancel = CancellationToken::new();
+        *self.current.lock().unwrap() = Some(cancel.clone());
+        *self.is_transforming.lock().unwrap() = true;
+
+        let status = TransformStatus {
+            tone_name: tone_name.to_string(),
+        };
+        let _ = handle.emit("transform-started", status.clone());
+
+        Ok(TransformGuard {
+            coordinator: self,
+            _slot: slot,
+            handle: handle.clone(),
+            status,
+            cancel,
+        })
+    }
+
+    pub fn is_transforming(&self) -> bool {
+        *self.is_transforming.lock().unwrap()
+    }
+
+    /// Cancels the running transformation; returns whether there was one
+    pub fn cancel_current(&self) -> bool {
+        match self.current.lock().unwrap().take() {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// A turn to transform, released when dropped
+pub struct TransformGuard<'a, R: Runtime> {
+    coordinator: &'a TransformCoordinator,
+    _slot: TokioMutexGuard<'a, ()>,
+    handle: AppHandle<R>,
+    status: TransformStatus,
+    cancel: CancellationToken,
+}
+
+impl<R: Runtime> TransformGuard<'_, R> {
+    /// Cancelled when the user cancels or a newer request replaces this one
+    pub fn cancel_token(&self) -> &CancellationToken {
+        &self.cancel
+    }
+}
+
+impl<R: Runtime> Drop for TransformGuard<'_, R> {
+    fn drop(&mut self) {
+        *self.coordinator.is_transforming.lock().unwrap() = false;
+        self.coordinator.current.lock().unwrap().take();
+        let _ = self.handle.emit("transform-finished", self.status.clone());
+    }
+}
@@ -0,0 +1,335 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::error::MiloError;
+use crate::providers::OpenAiCompatibleProvider;
+use crate::settings::{PromptDefinition, RetryPolicy};
+use crate::transform::{transform_text, transform_text_streaming, TransformOutput};
+
+/// Request timeout for tests that don't exercise timeouts
+pub const STUB_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// One scripted reply of the stub server
+#[derive(Debug, Clone)]
+pub struct StubResponse {
+    status: u16,
+    content_type: &'static str,
+    /// Extra headers, e.g. `Retry-After`
+    headers: Vec<(String, String)>,
+    body: String,
+    delay: Duration,
+}
+
+impl StubResponse {
+    /// A chat completion whose only choice says `text`
+    pub fn completion(text: &str) -> Self {
+        Self::json(
+            200,
+            json!({
+                "id": "chatcmpl-stub",
+                "object": "chat.completion",
+                "created": 0,
+                "model": "stub-model",
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": text },
+                    "finish_reason": "stop"
+                }]
+            }),
+        )
+    }
+
+    /// A chat completion without any choices
+    pub fn empty_choices() -> Self {
+        Self::json(
+            200,
+            json!({
+                "id": "chatcmpl-stub",
+                "object": "chat.completion",
+                "created": 0,
+                "model": "stub-model",
+                "choices": []
+            }),
+        )
+    }
+
+    /// A streamed chat completion sending each of `deltas` as its own event
+    pub fn stream(deltas: &[&str]) -> Self {
+        let mut body = String::new();
+        for delta in deltas {
+            let chunk = json!({
+                "id": "chatcmpl-stub",
+                "object": "chat.completion.chunk",
+                "created": 0,
+                "model": "stub-model",
+                "choices": [{
+                    "index": 0,
+                    "delta": { "content": delta },
+                    "finish_reason": null
+                }]
+            });
+            body.push_str(&format!("data: {}\n\n", chunk));
+        }
+        body.push_str("data: [DONE]\n\n");
+        Self {
+            content_type: "text/event-stream",
+            ..Self::raw(200, &body)
+        }
+    }
+
+    /// An error body in the shape OpenAI and LiteLLM send
+    pub fn error(status: u16, code: &str, kind: &str) -> Self {
+        Self::json(
+            status,
+            json!({
+                "error": {
+                    "message": format!("stub {} error", status),
+                    "type": kind,
+                    "param": null,
+                    "code": code
+                }
+            }),
+        )
+    }
+
+    /// Sends `body` as is, for replies that aren't valid JSON
+    pub fn raw(status: u16, body: &str) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            headers: Vec::new(),
+            body: body.to_string(),
+            delay: Duration::ZERO,
+        }
+    }
+
+    /// Asks the client to wait `seconds` before retrying
+    pub fn retry_after(mut self, seconds: u64) -> Self {
+        self.headers
+            .push(("Retry-After".to_string(), seconds.to_string()));
+        self
+    }
+
+    /// Waits this long before answering
+    pub fn delayed(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    fn json(status: u16, body: serde_json::Value) -> Self {
+        Self::raw(status, &body.to_string())
+    }
+}
+
+/// An OpenAI-compatible HTTP server on localhost that answers every request with
+/// the next scripted response; the last one repeats once the script runs out
+pub struct StubServer {
+    base_url: String,
+    /// Path and body of every request received
+    requests: Arc<Mutex<Vec<(String, String)>>>,
+    task: JoinHandle<()>,
+}
+
+impl StubServer {
+    pub async fn start(responses: Vec<StubResponse>) -> Self {
+        assert!(
+            !responses.is_empty(),
+            "the stub needs at least one response"
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}/v1", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let responses = Arc::new(responses);
+        let next = Arc::new(AtomicUsize::new(0));
+        let received = requests.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let responses = responses.clone();
+                let next = next.clone();
+                let received = received.clone();
+                tokio::spawn(async move {
+                    let Some(request) = read_request(&mut stream).await else {
+                        return;
+                    };
+                    received.lock().unwrap().push(request);
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let response = &responses[index.min(responses.len() - 1)];
+                    write_response(stream, response).await;
+                });
+            }
+        });
+
+        Self {
+            base_url,
+            requests,
+            task,
+        }
+    }
+
+    /// Base URL to configure a provider with, including the `/v1` prefix
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Bodies of the requests received so far
+    pub fn requests(&self) -> Vec<String> {
+        let requests = self.requests.lock().unwrap();
+        requests.iter().map(|(_, body)| body.clone()).collect()
+    }
+
+    /// Paths of the requests received so far, in order
+    pub fn paths(&self) -> Vec<String> {
+        let requests = self.requests.lock().unwrap();
+        requests.iter().map(|(path, _)| path.clone()).collect()
+    }
+}
+
+impl Drop for StubServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Provider sending its requests to `server`, giving up on each after `timeout`
+pub fn stub_provider(server: &StubServer, timeout: Duration) -> OpenAiCompatibleProvider {
+    let http = reqwest::Client::builder().timeout(timeout).build().unwrap();
+    OpenAiCompatibleProvider::new(
+        http,
+        "stub",
+        server.base_url().to_string(),
+        "test-key".to_string(),
+        "stub-model".to_string(),
+    )
+}
+
+/// Retry policy with millisecond delays, so retrying tests stay fast
+pub fn quick_retries(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts,
+        base_delay_ms: 1,
+        max_delay_ms: 5,
+        jitter: false,
+    }
+}
+
+/// Transforms a typo fix against a stub answering with `responses`
+pub async fn transform_with_stub(
+    responses: Vec<StubResponse>,
+    max_attempts: u32,
+    timeout: Duration,
+) -> (Result<TransformOutput, MiloError>, StubServer) {
+    let server = StubServer::start(responses).await;
+    let provider = stub_provider(&server, timeout);
+    let result = transform_text(
+        &provider,
+        "teh text",
+        &PromptDefinition::new("Fix typos:"),
+        &quick_retries(max_attempts),
+        &CancellationToken::new(),
+    )
+    .await;
+    (result, server)
+}
+
+/// Like `transform_with_stub` on the streaming path; also returns each progress report
+pub async fn stream_with_stub(
+    responses: Vec<StubResponse>,
+    max_attempts: u32,
+    timeout: Duration,
+) -> (Result<TransformOutput, MiloError>, Vec<String>, StubServer) {
+    let server = StubServer::start(responses).await;
+    let provider = stub_provider(&server, timeout);
+    let progress = Mutex::new(Vec::new());
+    let result = transform_text_streaming(
+        &provider,
+        "teh text",
+        &PromptDefinition::new("Fix typos:"),
+        &quick_retries(max_attempts),
+        &CancellationToken::new(),
+        &|partial: &str| progress.lock().unwrap().push(partial.to_string()),
+    )
+    .await;
+    (result, progress.into_inner().unwrap(), server)
+}
+
+// Reads one HTTP request and returns its path and body
+async fn read_request(stream: &mut TcpStream) -> Option<(String, String)> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        if let Some(position) = find(&buffer, b"\r\n\r\n") {
+            break position + 4;
+        }
+        match stream.read(&mut chunk).await.ok()? {
+            0 => return None,
+            read => buffer.extend_from_slice(&chunk[..read]),
+        }
+    };
+
+    let headers = String::from_utf8_lossy(&buffer[..header_end]).into_owned();
+    let path = headers
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or_default()
+        .to_string();
+    let headers = headers.to_lowercase();
+    let content_length = headers
+        .lines()
+        .find_map(|line| line.strip_prefix("content-length:"))
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    while buffer.len() < header_end + content_length {
+        match stream.read(&mut chunk).await.ok()? {
+            0 => return None,
+            read => buffer.extend_from_slice(&chunk[..read]),
+        }
+    }
+    let body = String::from_utf8_lossy(&buffer[header_end..]).into_owned();
+    Some((path, body))
+}
+
+async fn write_response(mut stream: TcpStream, response: &StubResponse) {
+    tokio::time::sleep(response.delay).await;
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        reason_phrase(response.status),
+        response.content_type,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    // The client may have timed out and hung up already
+    let _ = stream.write_all(head.as_bytes()).await;
+    let _ = stream.write_all(response.body.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
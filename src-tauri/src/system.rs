@@ -26,3 +26,22 @@ pub fn move_window_to_active_space(webview_window: &tauri::WebviewWindow) -> Res
     }
     Ok(())
 }
+
+/// Name of the application in front, used for the `{{app_name}}` prompt placeholder
+pub fn frontmost_app_name() -> Option<String> {
+    #[cfg(target_os = "macos")]
+    {
+        let output = std::process::Command::new("osascript")
+            .arg("-e")
+            .arg("tell application \"System Events\" to get name of first application process whose frontmost is true")
+            .output()
+            .ok()?;
+        let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !name.is_empty()).then_some(name)
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        None
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+
+/// Placeholders filled in by Milo itself; user-defined variables may not reuse these names
+pub const BUILTIN_VARIABLES: [&str; 4] = ["text", "date", "language", "app_name"];
+
+/// Values available while rendering a prompt template
+pub struct TemplateContext<'a> {
+    pub text: &'a str,
+    pub variables: &'a HashMap<String, String>,
+    /// Frontmost application, only looked up when the template asks for it
+    pub app_name: Option<&'a str>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment<'a> {
+    Literal(&'a str),
+    Variable(&'a str),
+}
+
+/// Hint added to syntax errors, since prompts may need literal braces
+const ESCAPE_HINT: &str = "write \\{{ for a literal '{{'";
+
+// Splits a template into literal text and `{{ name }}` placeholders. `\{{`
+// stands for a literal `{{`.
+fn parse(template: &str) -> Result<Vec<Segment<'_>>, String> {
+    let mut segments = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        if rest[..start].ends_with('\\') {
+            if start > 1 {
+                segments.push(Segment::Literal(&rest[..start - 1]));
+            }
+            segments.push(Segment::Literal("{{"));
+            rest = &rest[start + 2..];
+            continue;
+        }
+        if start > 0 {
+            segments.push(Segment::Literal(&rest[..start]));
+        }
+        let after_open = &rest[start + 2..];
+        let end = after_open
+            .find("}}")
+            .ok_or_else(|| format!("Unclosed '{{{{' in prompt template ({})", ESCAPE_HINT))?;
+
+        let name = after_open[..end].trim();
+        if !is_valid_name(name) {
+            return Err(format!(
+                "Invalid placeholder '{{{{{}}}}}' ({})",
+                &after_open[..end],
+                ESCAPE_HINT
+            ));
+        }
+        segments.push(Segment::Variable(name));
+        rest = &after_open[end + 2..];
+    }
+
+    if !rest.is_empty() {
+        segments.push(Segment::Literal(rest));
+    }
+    Ok(segments)
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Whether the template references the placeholder `name`
+pub fn uses_variable(template: &str, name: &str) -> bool {
+    parse(template)
+        .map(|segments| segments.contains(&Segment::Variable(name)))
+        .unwrap_or(false)
+}
+
+/// Escapes each `{{` that doesn't open a `{{ name }}` placeholder, so the
+/// template parses while its placeholders, known or not, stay as they are
+pub fn escape_invalid(template: &str) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after_open = &rest[start + 2..];
+        let placeholder = after_open
+            .find("}}")
+            .map(|end| &after_open[..end])
+            .filter(|inner| is_valid_name(inner.trim()));
+
+        match placeholder {
+            Some(inner) if !rest[..start].ends_with('\\') => {
+                output.push_str("{{");
+                output.push_str(inner);
+                output.push_str("}}");
+                rest = &after_open[inner.len() + 2..];
+            }
+            _ => {
+                if !rest[..start].ends_with('\\') {
+                    output.push('\\');
+                }
+                output.push_str("{{");
+                rest = after_open;
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// Checks that the template parses and only references known variables
+pub fn validate(template: &str, variables: &HashMap<String, String>) -> Result<(), String> {
+    for segment in parse(template)? {
+        if let Segment::Variable(name) = segment {
+            if !BUILTIN_VARIABLES.contains(&name) && !variables.contains_key(name) {
+                return Err(format!("Unknown placeholder '{{{{{}}}}}'", name));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks that a user-defined variable name is usable in templates
+pub fn validate_variable_name(name: &str) -> Result<(), String> {
+    if !is_valid_name(name) {
+        return Err(format!(
+            "Invalid variable name '{}': use letters, digits and underscores",
+            name
+        ));
+    }
+    if BUILTIN_VARIABLES.contains(&name) {
+        return Err(format!("'{}' is a built-in variable", name));
+    }
+    Ok(())
+}
+
+pub fn render(template: &str, context: &TemplateContext) -> Result<String, String> {
+    let mut output = String::with_capacity(template.len());
+
+    for segment in parse(template)? {
+        match segment {
+            Segment::Literal(literal) => output.push_str(literal),
+            Segment::Variable("text") => output.push_str(context.text),
+            Segment::Variable("date") => {
+                output.push_str(&chrono::Local::now().format("%Y-%m-%d").to_string())
+            }
+            Segment::Variable("language") => output.push_str(&system_language()),
+            Segment::Variable("app_name") => output.push_str(context.app_name.unwrap_or("Unknown")),
+            Segment::Variable(name) => {
+                let value = context
+                    .variables
+                    .get(name)
+                    .ok_or_else(|| format!("Unknown placeholder '{{{{{}}}}}'", name))?;
+                output.push_str(value);
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+// OS UI language as a BCP 47 tag such as "en-US"
+fn system_language() -> String {
+    sys_locale::get_locale().unwrap_or_else(|| "en-US".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context<'a>(text: &'a str, variables: &'a HashMap<String, String>) -> TemplateContext<'a> {
+        TemplateContext {
+            text,
+            variables,
+            app_name: Some("Mail"),
+        }
+    }
+
+    #[test]
+    fn test_render_builtin_and_user_variables() {
+        let mut variables = HashMap::new();
+        variables.insert("signature".to_string(), "Milo".to_string());
+
+        let rendered = render(
+            "Rewrite for {{ app_name }} and sign as {{signature}}: {{text}}",
+            &context("hi there", &variables),
+        )
+        .unwrap();
+
+        assert_eq!(rendered, "Rewrite for Mail and sign as Milo: hi there");
+    }
+
+    #[test]
+    fn test_plain_prompt_is_unchanged() {
+        let variables = HashMap::new();
+        let prompt = "Improve this text while maintaining its meaning:";
+        assert_eq!(render(prompt, &context("x", &variables)).unwrap(), prompt);
+        assert!(!uses_variable(prompt, "text"));
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_placeholders() {
+        let variables = HashMap::new();
+        assert!(validate("Use {{date}} and {{language}}", &variables).is_ok());
+        assert!(validate("Hello {{name}}", &variables).is_err());
+        assert!(validate("Hello {{text", &variables).is_err());
+        assert!(validate("Hello {{bad name}}", &variables).is_err());
+    }
+
+    #[test]
+    fn test_escaped_braces_are_literal() {
+        let variables = HashMap::new();
+        let template = "Answer as JSON like \\{{\"ok\": true}} for {{app_name}}";
+        assert!(validate(template, &variables).is_ok());
+        assert_eq!(
+            render(template, &context("x", &variables)).unwrap(),
+            "Answer as JSON like {{\"ok\": true}} for Mail"
+        );
+    }
+
+    #[test]
+    fn test_escape_invalid_keeps_placeholders() {
+        let variables = HashMap::new();
+        let legacy = "For {{app_name}} reply {{ \"a\": {{{b}}} }} and \\{{x, {{ team }}, {{";
+        assert!(parse(legacy).is_err());
+
+        let escaped = escape_invalid(legacy);
+        assert_eq!(
+            escaped,
+            "For {{app_name}} reply \\{{ \"a\": \\{{{b}}} }} and \\{{x, {{ team }}, \\{{"
+        );
+        assert!(parse(&escaped).is_ok());
+        assert_eq!(escape_invalid(&escaped), escaped);
+        // Placeholders for variables that don't exist are left for the user to fix
+        assert_eq!(
+            validate(&escaped, &variables),
+            Err("Unknown placeholder '{{team}}'".to_string())
+        );
+
+        let mut with_team = HashMap::new();
+        with_team.insert("team".to_string(), "Docs".to_string());
+        assert_eq!(
+            render(&escaped, &context("x", &with_team)).unwrap(),
+            "For Mail reply {{ \"a\": {{{b}}} }} and {{x, Docs, {{"
+        );
+    }
+
+    #[test]
+    fn test_validate_variable_name() {
+        assert!(validate_variable_name("team_name").is_ok());
+        assert!(validate_variable_name("text").is_err());
+        assert!(validate_variable_name("1st").is_err());
+    }
+}
@@ -1,66 +1,425 @@
-use crate::config::CONFIG;
-use async_openai::{
-    config::OpenAIConfig,
-    types::{
-        ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs,
-        CreateChatCompletionRequestArgs,
-    },
-    Client,
-};
-
-// Core transformation function that connects to LiteLLM proxy server
+use std::sync::Mutex;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::error::MiloError;
+use crate::providers::{CompletionRequest, OnDelta, TransformProvider};
+use crate::retry::with_retry;
+use crate::settings::{PromptDefinition, RetryPolicy};
+
+/// Completion text along with how many provider calls it took
+#[derive(Debug, Clone)]
+pub struct TransformOutput {
+    pub text: String,
+    pub attempts: u32,
+}
+
+fn build_request(
+    provider: &dyn TransformProvider,
+    text: &str,
+    prompt: &PromptDefinition,
+) -> CompletionRequest {
+    CompletionRequest {
+        model: prompt
+            .model
+            .clone()
+            .unwrap_or_else(|| provider.default_model().to_string()),
+        system_prompt: prompt.prompt.clone(),
+        text: text.to_string(),
+        temperature: prompt.temperature,
+        top_p: prompt.top_p,
+        max_tokens: prompt.max_tokens,
+        stop: prompt.stop.clone(),
+    }
+}
+
+// Core transformation function that sends the text to the configured provider.
+// Firing `cancel` drops the in-flight HTTP request, retries included.
 pub async fn transform_text(
+    provider: &dyn TransformProvider,
     text: &str,
-    prompt: &str,
-    litellm_api_key: &str,
-) -> Result<String, String> {
-    let config = OpenAIConfig::new()
-        .with_api_key(litellm_api_key)
-        .with_api_base(&CONFIG.litellm_base_url);
-    let client = Client::with_config(config);
-
-    let request = CreateChatCompletionRequestArgs::default()
-        .model(&CONFIG.default_model)
-        .messages([
-            ChatCompletionRequestSystemMessageArgs::default()
-                .content(prompt)
-                .build()
-                .map_err(|e| format!("Failed to build system message: {}", e))?
-                .into(),
-            ChatCompletionRequestUserMessageArgs::default()
-                .content(text)
-                .build()
-                .map_err(|e| format!("Failed to build user message: {}", e))?
-                .into(),
-        ])
-        .build()
-        .map_err(|e| format!("Failed to build chat completion request: {}", e))?;
-
-    match client.chat().create(request).await {
-        Ok(response) => response
-            .choices
-            .first()
-            .and_then(|choice| choice.message.content.clone())
-            .ok_or_else(|| "No completion choices returned from API".to_string()),
-        Err(e) => {
-            let error_msg = e.to_string();
-            if error_msg.contains("429")
-                || error_msg.contains("rate limit")
-                || error_msg.contains("Rate limit")
-            {
-                Err("Rate limit exceeded - please top up your account balance".to_string())
-            } else if error_msg.contains("401") || error_msg.contains("unauthorized") {
-                Err("Invalid API key - please check your credentials".to_string())
-            } else if error_msg.contains("403") || error_msg.contains("forbidden") {
-                Err("API access forbidden - please check your account status".to_string())
-            } else if error_msg.contains("insufficient")
-                || error_msg.contains("quota")
-                || error_msg.contains("billing")
-            {
-                Err("Rate limit exceeded - please top up your account balance".to_string())
-            } else {
-                Err(format!("API error: {}", e))
+    prompt: &PromptDefinition,
+    retry_policy: &RetryPolicy,
+    cancel: &CancellationToken,
+) -> Result<TransformOutput, MiloError> {
+    let attempt = with_retry(retry_policy, |_| {
+        provider.complete(build_request(provider, text, prompt))
+    });
+
+    tokio::select! {
+        _ = cancel.cancelled() => Err(MiloError::Cancelled),
+        (result, attempts) = attempt => result.map(|text| TransformOutput { text, attempts }),
+    }
+}
+
+/// Alternative completions for one request
+#[derive(Debug, Clone)]
+pub struct CandidatesOutput {
+    pub texts: Vec<String>,
+    pub attempts: u32,
+}
+
+// Asks the provider for `n` alternative rewrites of the text
+pub async fn transform_candidates(
+    provider: &dyn TransformProvider,
+    text: &str,
+    prompt: &PromptDefinition,
+    retry_policy: &RetryPolicy,
+    cancel: &CancellationToken,
+    n: u32,
+) -> Result<CandidatesOutput, MiloError> {
+    let attempt = with_retry(retry_policy, |_| {
+        provider.complete_candidates(build_request(provider, text, prompt), n)
+    });
+
+    tokio::select! {
+        _ = cancel.cancelled() => Err(MiloError::Cancelled),
+        (result, attempts) = attempt => result.map(|texts| CandidatesOutput { texts, attempts }),
+    }
+}
+
+// Streaming variant: reports the accumulated text through `on_progress` and
+// stops early (dropping the HTTP stream) once `cancel` fires
+pub async fn transform_text_streaming(
+    provider: &dyn TransformProvider,
+    text: &str,
+    prompt: &PromptDefinition,
+    retry_policy: &RetryPolicy,
+    cancel: &CancellationToken,
+    on_progress: &OnDelta<'_>,
+) -> Result<TransformOutput, MiloError> {
+    let partial = Mutex::new(String::new());
+    let on_delta = |delta: &str| {
+        let mut partial = partial.lock().unwrap();
+        partial.push_str(delta);
+        on_progress(&partial);
+    };
+
+    let attempt_stream = with_retry(retry_policy, |_| {
+        // A retried stream starts over, so drop whatever the failed attempt produced
+        partial.lock().unwrap().clear();
+        provider.complete_stream(build_request(provider, text, prompt), &on_delta)
+    });
+
+    tokio::select! {
+        _ = cancel.cancelled() => Err(MiloError::Cancelled),
+        (result, attempts) = attempt_stream => result.map(|text| TransformOutput { text, attempts }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub_server::{
+        quick_retries, stream_with_stub, stub_provider, transform_with_stub, StubResponse,
+        StubServer, STUB_TIMEOUT,
+    };
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_returns_completion() {
+        let (result, server) =
+            transform_with_stub(vec![StubResponse::completion("the text")], 3, STUB_TIMEOUT).await;
+
+        let output = result.unwrap();
+        assert_eq!(output.text, "the text");
+        assert_eq!(output.attempts, 1);
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].contains("Fix typos:"));
+        assert!(requests[0].contains("teh text"));
+    }
+
+    #[tokio::test]
+    async fn test_retries_rate_limit() {
+        let (result, server) = transform_with_stub(
+            vec![
+                StubResponse::error(429, "rate_limit_exceeded", "requests"),
+                StubResponse::completion("the text"),
+            ],
+            3,
+            STUB_TIMEOUT,
+        )
+        .await;
+
+        assert_eq!(result.unwrap().attempts, 2);
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_when_rate_limit_persists() {
+        let (result, server) = transform_with_stub(
+            vec![StubResponse::error(429, "rate_limit_exceeded", "requests")],
+            2,
+            STUB_TIMEOUT,
+        )
+        .await;
+
+        assert_eq!(
+            result.unwrap_err(),
+            MiloError::RateLimited { retry_after: None }
+        );
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_retries_server_errors() {
+        let (result, server) = transform_with_stub(
+            vec![
+                StubResponse::raw(500, "Internal Server Error"),
+                StubResponse::error(503, "", ""),
+                StubResponse::completion("the text"),
+            ],
+            3,
+            STUB_TIMEOUT,
+        )
+        .await;
+
+        assert_eq!(result.unwrap().attempts, 3);
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_server_error_keeps_retry_after() {
+        let (result, server) = transform_with_stub(
+            vec![StubResponse::raw(503, "busy").retry_after(7)],
+            2,
+            STUB_TIMEOUT,
+        )
+        .await;
+
+        assert_eq!(
+            result.unwrap_err(),
+            MiloError::Server {
+                status: 503,
+                retry_after: Some(Duration::from_secs(7)),
             }
-        }
+        );
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_quota_errors_are_not_retried() {
+        let (result, server) = transform_with_stub(
+            vec![StubResponse::error(
+                429,
+                "insufficient_quota",
+                "insufficient_quota",
+            )],
+            3,
+            STUB_TIMEOUT,
+        )
+        .await;
+
+        assert_eq!(result.unwrap_err(), MiloError::QuotaExceeded);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_auth_errors_are_not_retried() {
+        let (result, server) = transform_with_stub(
+            vec![StubResponse::error(
+                401,
+                "invalid_api_key",
+                "invalid_request_error",
+            )],
+            3,
+            STUB_TIMEOUT,
+        )
+        .await;
+        assert_eq!(result.unwrap_err(), MiloError::Unauthorized);
+        assert_eq!(server.requests().len(), 1);
+
+        let (result, server) = transform_with_stub(
+            vec![StubResponse::error(403, "403", "auth_error")],
+            3,
+            STUB_TIMEOUT,
+        )
+        .await;
+        assert_eq!(result.unwrap_err(), MiloError::Forbidden);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_json() {
+        let (result, _server) = transform_with_stub(
+            vec![StubResponse::raw(200, "{\"choices\": [")],
+            3,
+            STUB_TIMEOUT,
+        )
+        .await;
+
+        assert!(matches!(result, Err(MiloError::Api(_))));
+    }
+
+    #[tokio::test]
+    async fn test_empty_choices() {
+        let (result, _server) =
+            transform_with_stub(vec![StubResponse::empty_choices()], 3, STUB_TIMEOUT).await;
+
+        assert_eq!(result.unwrap_err(), MiloError::EmptyCompletion);
+    }
+
+    #[tokio::test]
+    async fn test_slow_response_times_out() {
+        let server = StubServer::start(vec![
+            StubResponse::completion("the text").delayed(Duration::from_secs(2))
+        ])
+        .await;
+        let provider = stub_provider(&server, Duration::from_millis(100));
+
+        let result = transform_text(
+            &provider,
+            "teh text",
+            &PromptDefinition::new("Fix typos:"),
+            &quick_retries(1),
+            &CancellationToken::new(),
+        )
+        .await;
+
+        assert_eq!(result.unwrap_err(), MiloError::Timeout);
+    }
+
+    #[tokio::test]
+    async fn test_streams_partial_text() {
+        let (result, progress, server) = stream_with_stub(
+            vec![StubResponse::stream(&["the ", "text"])],
+            3,
+            STUB_TIMEOUT,
+        )
+        .await;
+
+        let output = result.unwrap();
+        assert_eq!(output.text, "the text");
+        assert_eq!(output.attempts, 1);
+        assert_eq!(progress, vec!["the ", "the text"]);
+        assert!(server.requests()[0].contains("\"stream\":true"));
+    }
+
+    #[tokio::test]
+    async fn test_streamed_errors_are_classified() {
+        let (result, _, server) = stream_with_stub(
+            vec![StubResponse::error(
+                401,
+                "invalid_api_key",
+                "invalid_request_error",
+            )],
+            3,
+            STUB_TIMEOUT,
+        )
+        .await;
+        assert_eq!(result.unwrap_err(), MiloError::Unauthorized);
+        assert_eq!(server.requests().len(), 1);
+
+        let (result, _, _) = stream_with_stub(
+            vec![StubResponse::error(403, "403", "auth_error")],
+            3,
+            STUB_TIMEOUT,
+        )
+        .await;
+        assert_eq!(result.unwrap_err(), MiloError::Forbidden);
+
+        let (result, _, _) = stream_with_stub(
+            vec![StubResponse::error(
+                429,
+                "insufficient_quota",
+                "insufficient_quota",
+            )],
+            3,
+            STUB_TIMEOUT,
+        )
+        .await;
+        assert_eq!(result.unwrap_err(), MiloError::QuotaExceeded);
+    }
+
+    #[tokio::test]
+    async fn test_streamed_rate_limit_is_retried() {
+        let (result, progress, server) = stream_with_stub(
+            vec![
+                StubResponse::error(429, "rate_limit_exceeded", "requests"),
+                StubResponse::stream(&["the text"]),
+            ],
+            3,
+            STUB_TIMEOUT,
+        )
+        .await;
+
+        assert_eq!(result.unwrap().attempts, 2);
+        assert_eq!(progress, vec!["the text"]);
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_streamed_server_errors_are_retried() {
+        let (result, _, server) = stream_with_stub(
+            vec![
+                StubResponse::raw(500, "Internal Server Error"),
+                StubResponse::raw(503, "Service Unavailable"),
+                StubResponse::stream(&["the text"]),
+            ],
+            3,
+            STUB_TIMEOUT,
+        )
+        .await;
+
+        assert_eq!(result.unwrap().attempts, 3);
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_slow_stream_times_out() {
+        let (result, progress, _server) = stream_with_stub(
+            vec![StubResponse::stream(&["the text"]).delayed(Duration::from_secs(2))],
+            1,
+            Duration::from_millis(100),
+        )
+        .await;
+
+        assert_eq!(result.unwrap_err(), MiloError::Timeout);
+        assert!(progress.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_error_inside_stream() {
+        let (result, _, _server) = stream_with_stub(
+            vec![StubResponse::raw(
+                200,
+                "data: {\"error\":{\"message\":\"upstream failed\"}}\n\n",
+            )],
+            1,
+            STUB_TIMEOUT,
+        )
+        .await;
+
+        assert_eq!(
+            result.unwrap_err(),
+            MiloError::Api("upstream failed".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancel_drops_request() {
+        let server = StubServer::start(vec![
+            StubResponse::completion("the text").delayed(Duration::from_secs(2))
+        ])
+        .await;
+        let provider = stub_provider(&server, STUB_TIMEOUT);
+        let cancel = CancellationToken::new();
+        let canceller = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            canceller.cancel();
+        });
+
+        let result = transform_text(
+            &provider,
+            "teh text",
+            &PromptDefinition::new("Fix typos:"),
+            &quick_retries(3),
+            &cancel,
+        )
+        .await;
+
+        assert_eq!(result.unwrap_err(), MiloError::Cancelled);
     }
 }
@@ -1,7 +1,7 @@
 use tauri::{
     menu::MenuBuilder,
     tray::{TrayIcon, TrayIconBuilder},
-    App, AppHandle, Manager,
+    App, AppHandle, Manager, Runtime,
 };
 
 use crate::core;
@@ -9,11 +9,16 @@ use crate::core;
 use crate::system;
 use tauri::Emitter;
 
+/// Id of the menu bar icon
+pub const TRAY_ID: &str = "main";
+
 pub fn create_tray_menu(app: &App) -> Result<TrayIcon, tauri::Error> {
     println!("Creating tray menu...");
 
     let menu = MenuBuilder::new(app)
         .text("transform", "Transform")
+        .text("cancel", "Cancel Transform")
+        .text("undo", "Undo Last Transform")
         .separator()
         .text("dashboard", "Dashboard")
         .text("prompts", "Edit Tone Prompts")
@@ -22,7 +27,7 @@ pub fn create_tray_menu(app: &App) -> Result<TrayIcon, tauri::Error> {
         .text("quit", "Quit")
         .build()?;
 
-    let tray = TrayIconBuilder::new()
+    let tray = TrayIconBuilder::with_id(TRAY_ID)
         .icon(app.default_window_icon().unwrap().clone())
         .menu(&menu)
         .show_menu_on_left_click(true)
@@ -32,6 +37,20 @@ pub fn create_tray_menu(app: &App) -> Result<TrayIcon, tauri::Error> {
     Ok(tray)
 }
 
+/// Flags a problem with the API key on the tray icon; None clears the flag
+pub fn show_key_warning<R: Runtime>(app: &AppHandle<R>, warning: Option<&str>) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let tooltip = match warning {
+        Some(warning) => format!("Milo - {}", warning),
+        None => "Milo".to_string(),
+    };
+    let _ = tray.set_tooltip(Some(tooltip));
+    // Shown next to the icon in the macOS menu bar and as the indicator label on Linux
+    let _ = tray.set_title(warning.map(|_| "⚠️"));
+}
+
 fn handle_menu_event(app: &AppHandle, event: tauri::menu::MenuEvent) {
     println!("Menu event received: {:?}", event.id());
     match event.id().as_ref() {
@@ -50,35 +69,31 @@ fn handle_menu_event(app: &AppHandle, event: tauri::menu::MenuEvent) {
             println!("Settings menu item clicked");
             show_window_and_navigate(app, "api");
         }
-        "transform" => {
+        "cancel" => {
             let state = app.state::<crate::state::AppState>();
-            let is_transforming = *state.is_transforming.lock().unwrap();
-            if !is_transforming {
-                println!("Starting transformation...");
-                let app_handle = app.clone();
-                tauri::async_runtime::spawn(async move {
-                    if let Err(e) =
-                        core::transform_clip_with_setting(app_handle.clone(), false).await
-                    {
-                        println!("Transform error: {}", e);
-
-                        // Show notification for rate limit errors
-                        if e.contains("rate limit") || e.contains("Rate limit") {
-                            let notification_handle = app_handle.clone();
-                            tokio::spawn(async move {
-                                crate::notifications::show_notification(
-                                    &notification_handle,
-                                    "Milo - Rate Limited",
-                                    "Not enough API balance! Please top up your account and try again.",
-                                );
-                            });
-                        }
-                    }
-                });
-            } else {
-                println!("Transformation already in progress");
+            if !state.transforms.cancel_current() {
+                println!("No transformation to cancel");
             }
         }
+        "undo" => {
+            println!("Undo menu item clicked");
+            core::undo_with_notification(app);
+        }
+        "transform" => {
+            // The transform coordinator decides what happens if one is already running
+            println!("Starting transformation...");
+            let app_handle = app.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = core::transform_clip_with_setting(app_handle.clone(), false).await {
+                    println!("Transform error: {}", e);
+
+                    let notification_handle = app_handle.clone();
+                    tokio::spawn(async move {
+                        crate::notifications::show_error_notification(&notification_handle, &e);
+                    });
+                }
+            });
+        }
         _ => {
             println!("Unknown menu item clicked: {:?}", event.id());
         }
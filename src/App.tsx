@@ -4,16 +4,17 @@ import { listen } from "@tauri-apps/api/event";
 import { isPermissionGranted, requestPermission, sendNotification } from '@tauri-apps/plugin-notification';
 import { ThemeProvider } from "./context/ThemeContext";
 import { Settings } from "./components/ApiSettings";
-import { PromptSettings } from "./components/PromptSettings";
+import { PromptSettings, PromptDefinition } from "./components/PromptSettings";
 import { Sidebar } from "./components/Sidebar";
 import { InfoPage } from "./components/InfoPage";
 import { History } from "./components/History";
 import { Dashboard } from "./components/Dashboard";
+import { TransformProgress } from "./components/TransformProgress";
   
 interface Settings {
   openai_model: string;
   custom_prompts: {
-    [key: string]: string;
+    [key: string]: PromptDefinition;
   };
   prompt_order: string[];
   selected_tone?: string;
@@ -135,6 +136,7 @@ function App() {
           {renderContent()}
         </div>
       </div>
+      <TransformProgress />
     </ThemeProvider>
   );
 }
@@ -1,28 +1,37 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { ask, message } from '@tauri-apps/plugin-dialog';
 import { open } from '@tauri-apps/plugin-shell';
 import { ShortcutItem } from "./ShortcutItem";
 import { ThemeSelector } from "./ThemeSelector";
 import { useShortcutEditor } from "../hooks/useShortcutEditor";
 import { backendFormatToShortcut, shortcutToBackendFormat, Shortcut } from "../utils/keyboardUtils";
-import { CONFIG } from "../config";
+import { CONFIG, getAppConfig } from "../config";
+import type { PromptDefinition } from "./PromptSettings";
 
 interface Settings {
   openai_model: string;
   custom_prompts: {
-    [key: string]: string;
+    [key: string]: PromptDefinition;
   };
   selected_tone?: string;
   firstVisitComplete?: boolean;
   shortcutEnabled?: boolean;
 }
 
+interface KeyStatus {
+  valid: boolean;
+  error: string | null;
+  models: string[];
+  remaining_budget: number | null;
+}
 
 export function Settings() {
   const [usageKey, setUsageKey] = useState("");
   const [usageKeyPreview, setUsageKeyPreview] = useState("");
   const [hasKey, setHasKey] = useState(false);
+  const [keyStatus, setKeyStatus] = useState<KeyStatus | null>(null);
   const [settings, setSettings] = useState<Settings>({
     openai_model: "",
     custom_prompts: {},
@@ -37,6 +46,15 @@ export function Settings() {
 
   useEffect(() => {
     loadSettings();
+
+    // Saving a key checks it in the background and reports here
+    const unlisten = listen<KeyStatus>('key-status-changed', (event) => {
+      setKeyStatus(event.payload);
+    });
+
+    return () => {
+      unlisten.then(fn => fn());
+    };
   }, []);
 
   const loadSettings = async () => {
@@ -51,6 +69,12 @@ export function Settings() {
       setUsageKeyPreview(keyPreview || '');
       setHasKey(!!keyPreview);
 
+      if (keyPreview) {
+        invoke<KeyStatus>('validate_api_key')
+          .then(setKeyStatus)
+          .catch(error => console.error('Failed to check usage key:', error));
+      }
+
       // Load general settings
       const savedSettings = await invoke<Settings>("get_settings");
       setSettings(savedSettings);
@@ -100,8 +124,8 @@ export function Settings() {
 
   const openWebsite = async () => {
     try {
-      const url = CONFIG?.website_url ?? '';
-      await open(url);
+      const { website_url } = await getAppConfig();
+      await open(website_url);
     } catch (error) {
       console.error('Failed to open website:', error);
       const fallbackUrl = CONFIG?.website_url ?? '';
@@ -294,10 +318,18 @@ export function Settings() {
             /* Has Key State */
             <div className="space-y-4">
               <div className="flex items-center justify-between">
-                <div className="inline-flex items-center px-3 py-1 rounded-full text-xs bg-green-100 text-green-800 dark:bg-green-900/20 dark:text-green-400">
-                  <div className="w-2 h-2 bg-green-500 rounded-full mr-2"></div>
-                  Ready
-                </div>
+                {keyStatus && !keyStatus.valid ? (
+                  <div className="inline-flex items-center px-3 py-1 rounded-full text-xs bg-red-100 text-red-800 dark:bg-red-900/20 dark:text-red-400">
+                    <div className="w-2 h-2 bg-red-500 rounded-full mr-2"></div>
+                    {keyStatus.error ?? 'Invalid key'}
+                  </div>
+                ) : (
+                  <div className="inline-flex items-center px-3 py-1 rounded-full text-xs bg-green-100 text-green-800 dark:bg-green-900/20 dark:text-green-400">
+                    <div className="w-2 h-2 bg-green-500 rounded-full mr-2"></div>
+                    Ready
+                    {keyStatus?.remaining_budget != null && ` · $${keyStatus.remaining_budget.toFixed(2)} left`}
+                  </div>
+                )}
                 <button
                   onClick={deleteUsageKey}
                   className="px-3 py-1.5 text-xs text-text-secondary hover:text-text-primary transition-colors"
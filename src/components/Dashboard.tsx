@@ -2,7 +2,7 @@ import { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { open } from '@tauri-apps/plugin-shell';
 import { BarChart, Bar, XAxis, YAxis, Tooltip, ResponsiveContainer } from 'recharts';
-import { CONFIG } from '../config';
+import { getAppConfig } from '../config';
 
 interface DayStats {
   date: string;
@@ -48,7 +48,8 @@ export function Dashboard() {
 
   const openWebsite = async () => {
     try {
-      await open(CONFIG.website_url);
+      const config = await getAppConfig();
+      await open(config.website_url);
     } catch (error) {
       console.error('Failed to open website:', error);
     }
@@ -23,10 +23,20 @@ import {
 } from '@dnd-kit/sortable';
 import { CSS } from '@dnd-kit/utilities';
 
+export interface PromptDefinition {
+  prompt: string;
+  model?: string | null;
+  temperature?: number | null;
+  top_p?: number | null;
+  max_tokens?: number | null;
+  stop?: string[] | null;
+  candidates?: number | null;
+}
+
 interface Settings {
   openai_model: string;
   custom_prompts: {
-    [key: string]: string;
+    [key: string]: PromptDefinition;
   };
   prompt_order: string[];
   selected_tone?: string;
@@ -211,7 +221,8 @@ export function PromptSettings({ settings, setSettings }: PromptSettingsProps) {
         ...settings,
         custom_prompts: {
           ...settings.custom_prompts,
-          [name]: prompt,
+          // Keep the tone's model parameters when only the prompt text changes
+          [name]: { ...settings.custom_prompts[name], prompt },
         },
         prompt_order: updatedPromptOrder,
         selected_tone: settings.selected_tone || name, // Only set if none selected
@@ -248,7 +259,7 @@ export function PromptSettings({ settings, setSettings }: PromptSettingsProps) {
   const handleEdit = (name: string) => {
     setEditingTone({
       name,
-      prompt: settings.custom_prompts[name]
+      prompt: settings.custom_prompts[name].prompt
     });
     setIsFormOpen(true);
   };
@@ -357,7 +368,7 @@ export function PromptSettings({ settings, setSettings }: PromptSettingsProps) {
                   <SortablePromptItem
                     key={name}
                     name={name}
-                    prompt={settings.custom_prompts[name]}
+                    prompt={settings.custom_prompts[name].prompt}
                     isSelected={settings.selected_tone === name}
                     onSelect={handleSelectTone}
                     onEdit={handleEdit}
@@ -371,7 +382,7 @@ export function PromptSettings({ settings, setSettings }: PromptSettingsProps) {
               {activeId ? (
                 <SortablePromptItem
                   name={activeId}
-                  prompt={settings.custom_prompts[activeId]}
+                  prompt={settings.custom_prompts[activeId].prompt}
                   isSelected={settings.selected_tone === activeId}
                   onSelect={() => {}}
                   onEdit={() => {}}
@@ -0,0 +1,162 @@
+import { useState, useEffect } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import { Button } from './ui/button';
+
+interface WordDiff {
+  word: string;
+  change_type: 'added' | 'removed' | 'unchanged';
+  position: number;
+}
+
+interface TextDiff {
+  original_diff: WordDiff[];
+  transformed_diff: WordDiff[];
+  added_count: number;
+  removed_count: number;
+}
+
+interface ReviewCandidate {
+  transformed_text: string;
+  diff: TextDiff;
+}
+
+interface ReviewPayload {
+  tone_name: string;
+  original_text: string;
+  candidates: ReviewCandidate[];
+}
+
+type ReviewDecision = 'accept' | 'reject' | 'retry';
+
+// Floating window that shows a transform result before it reaches the clipboard,
+// and lets the user pick one when the tone generates several candidates
+export function ReviewWindow() {
+  const [review, setReview] = useState<ReviewPayload | null>(null);
+  const [selected, setSelected] = useState(0);
+  const [retrying, setRetrying] = useState(false);
+
+  useEffect(() => {
+    invoke<ReviewPayload | null>('get_pending_review')
+      .then(setReview)
+      .catch(error => console.error('Failed to load review:', error));
+
+    // A retry sends the new result to this same window
+    const unlisten = listen<ReviewPayload>('review-updated', (event) => {
+      setReview(event.payload);
+      setSelected(0);
+      setRetrying(false);
+    });
+
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+
+  const resolve = async (decision: ReviewDecision) => {
+    if (decision === 'retry') {
+      setRetrying(true);
+    }
+    try {
+      await invoke('resolve_review', { decision, candidate: selected });
+    } catch (error) {
+      console.error('Failed to resolve review:', error);
+      setRetrying(false);
+    }
+  };
+
+  const renderDiffText = (words: WordDiff[], isTransformed: boolean) => {
+    return (
+      <div className="font-mono text-sm leading-relaxed">
+        {words.map((wordDiff, index) => {
+          let className = '';
+
+          if (wordDiff.change_type === 'removed' && !isTransformed) {
+            className = 'bg-red-100 text-red-800 px-1 rounded';
+          } else if (wordDiff.change_type === 'added' && isTransformed) {
+            className = 'bg-green-100 text-green-800 px-1 rounded';
+          } else if (wordDiff.change_type === 'unchanged') {
+            className = 'text-text-primary';
+          }
+
+          return (
+            <span key={index} className={className}>
+              {wordDiff.word}
+              {index < words.length - 1 ? ' ' : ''}
+            </span>
+          );
+        })}
+      </div>
+    );
+  };
+
+  if (!review) {
+    return (
+      <div className="p-6 text-sm text-text-secondary">Waiting for a transformation...</div>
+    );
+  }
+
+  const candidate = review.candidates[selected] ?? review.candidates[0];
+
+  return (
+    <div className="flex flex-col h-screen bg-background-primary">
+      <div className="flex items-center justify-between p-4 border-b border-border-primary">
+        <span className="px-2 py-1 text-xs bg-accent-primary/10 text-accent-primary rounded">
+          {review.tone_name}
+        </span>
+        <span className="text-xs text-text-secondary bg-background-tertiary px-2 py-1 rounded">
+          <span className="text-green-600">+{candidate.diff.added_count}</span>
+          {' / '}
+          <span className="text-red-600">-{candidate.diff.removed_count}</span>
+        </span>
+      </div>
+
+      {review.candidates.length > 1 && (
+        <div className="flex gap-2 px-4 pt-4">
+          {review.candidates.map((_, index) => (
+            <Button
+              key={index}
+              size="sm"
+              variant={index === selected ? 'default' : 'outline'}
+              disabled={retrying}
+              onClick={() => setSelected(index)}
+            >
+              Option {index + 1}
+            </Button>
+          ))}
+        </div>
+      )}
+
+      <div className="flex-1 overflow-y-auto p-4 space-y-4">
+        <div>
+          <span className="text-sm text-text-primary">Original</span>
+          <div className="mt-2 border border-border-primary rounded-lg p-3 bg-background-tertiary">
+            {renderDiffText(candidate.diff.original_diff, false)}
+          </div>
+        </div>
+        <div>
+          <span className="text-sm text-text-primary">Transformed</span>
+          <div className="mt-2 border border-border-primary rounded-lg p-3 bg-background-tertiary">
+            {retrying ? (
+              <div className="animate-pulse text-sm text-text-secondary">Retrying...</div>
+            ) : (
+              renderDiffText(candidate.diff.transformed_diff, true)
+            )}
+          </div>
+        </div>
+      </div>
+
+      <div className="flex justify-end gap-2 p-4 border-t border-border-primary">
+        <Button variant="ghost" disabled={retrying} onClick={() => resolve('reject')}>
+          Reject
+        </Button>
+        <Button variant="outline" disabled={retrying} onClick={() => resolve('retry')}>
+          Retry
+        </Button>
+        <Button disabled={retrying} onClick={() => resolve('accept')}>
+          Accept
+        </Button>
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,92 @@
+import { useState, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { Button } from "./ui/button";
+
+interface TransformStatus {
+  tone_name: string;
+}
+
+interface TransformProgressPayload {
+  tone_name: string;
+  step: number;
+  total_steps: number;
+  partial_text: string;
+}
+
+// Only the end of a long response fits in the card
+const PREVIEW_CHARS = 280;
+
+export function TransformProgress() {
+  const [toneName, setToneName] = useState<string | null>(null);
+  const [progress, setProgress] = useState<TransformProgressPayload | null>(null);
+
+  useEffect(() => {
+    // Pick up a transformation that started before the window loaded
+    invoke<boolean>("is_transforming")
+      .then((running) => {
+        if (running) setToneName((current) => current ?? "");
+      })
+      .catch(console.error);
+
+    const unlistenStarted = listen<TransformStatus>("transform-started", (event) => {
+      setToneName(event.payload.tone_name);
+      setProgress(null);
+    });
+    const unlistenProgress = listen<TransformProgressPayload>("transform-progress", (event) => {
+      setToneName(event.payload.tone_name);
+      setProgress(event.payload);
+    });
+    const unlistenFinished = listen("transform-finished", () => {
+      setToneName(null);
+      setProgress(null);
+    });
+
+    return () => {
+      unlistenStarted.then(fn => fn());
+      unlistenProgress.then(fn => fn());
+      unlistenFinished.then(fn => fn());
+    };
+  }, []);
+
+  if (toneName === null) {
+    return null;
+  }
+
+  const cancel = async () => {
+    try {
+      await invoke("cancel_transformation");
+    } catch (error) {
+      console.error("Failed to cancel transformation:", error);
+    }
+  };
+
+  const partialText = progress?.partial_text ?? "";
+  const preview = partialText.length > PREVIEW_CHARS
+    ? "…" + partialText.slice(-PREVIEW_CHARS)
+    : partialText;
+
+  return (
+    <div className="fixed bottom-4 right-4 w-80 bg-background-secondary border border-border-primary rounded-lg shadow-lg p-4 z-50">
+      <div className="flex items-center justify-between gap-2">
+        <div className="flex items-center gap-2 min-w-0">
+          <span className="w-2 h-2 rounded-full bg-accent-primary animate-pulse shrink-0" />
+          <span className="text-sm text-text-primary truncate">
+            {toneName || "Transforming"}
+          </span>
+          {progress && progress.total_steps > 1 && (
+            <span className="text-xs text-text-secondary shrink-0">
+              Step {progress.step + 1} of {progress.total_steps}
+            </span>
+          )}
+        </div>
+        <Button variant="ghost" size="sm" onClick={cancel}>
+          Cancel
+        </Button>
+      </div>
+      <p className="mt-2 text-xs text-text-secondary whitespace-pre-wrap break-words max-h-32 overflow-hidden">
+        {preview || "Waiting for the response…"}
+      </p>
+    </div>
+  );
+}
@@ -1,7 +1,24 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// Built-in defaults; the backend may override them at runtime
 export const CONFIG = {
   litellm_base_url: "https://milo-litellm.up.railway.app",
   website_url: "https://milomilo.work/",
   default_model: "gpt-4o-mini"
 } as const;
 
-export type AppConfig = typeof CONFIG;
\ No newline at end of file
+export interface AppConfig {
+  litellm_base_url: string;
+  website_url: string;
+  default_model: string;
+}
+
+// Config in effect, after the user's config.json and MILO_* environment variables
+export async function getAppConfig(): Promise<AppConfig> {
+  try {
+    return await invoke<AppConfig>('get_app_config');
+  } catch (error) {
+    console.error('Failed to load app config:', error);
+    return CONFIG;
+  }
+}
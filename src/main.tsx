@@ -1,10 +1,13 @@
 import React from "react";
 import ReactDOM from "react-dom/client";
+import { getCurrentWindow } from "@tauri-apps/api/window";
 import App from "./App";
+import { ThemeProvider } from "./context/ThemeContext";
+import { ReviewWindow } from "./components/ReviewWindow";
 import "./main.css";
 
-// Get the window label
-const windowLabel = document.querySelector('meta[name="tauri-window"]')?.getAttribute("content");
+// Every window loads index.html; the window label decides what it shows
+const windowLabel = getCurrentWindow().label;
 console.log("Window label:", windowLabel);
 
 const rootElement = document.getElementById("root");
@@ -18,6 +21,15 @@ if (windowLabel === "main") {
       <App />
     </React.StrictMode>,
   );
+} else if (windowLabel === "review") {
+  console.log("Rendering review window");
+  ReactDOM.createRoot(rootElement as HTMLElement).render(
+    <React.StrictMode>
+      <ThemeProvider>
+        <ReviewWindow />
+      </ThemeProvider>
+    </React.StrictMode>,
+  );
 } else {
   console.log("Not rendering App - window label doesn't match");
 }